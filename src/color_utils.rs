@@ -4,6 +4,17 @@ pub fn grey(coeff: f64) -> String {
   format!("#{:0>2x}{:0>2x}{:0>2x}", v, v, v)
 }
 
+pub fn rgb((r, g, b): (u8, u8, u8)) -> String {
+  format!("#{:0>2x}{:0>2x}{:0>2x}", r, g, b)
+}
+
+/// A color between `from` (coeff = 0) and `to` (coeff = 1).
+pub fn blend(from: (u8, u8, u8), to: (u8, u8, u8), coeff: f64) -> String {
+  let coeff = f64::min(f64::max(coeff, 0.0), 1.0);
+  let mix = |from: u8, to: u8| (from as f64 + (to as f64 - from as f64) * coeff).round() as u8;
+  rgb((mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2)))
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -32,4 +43,30 @@ mod tests {
   fn grey_1_5_returns_white() {
     assert_eq!(grey(1.5), "#ffffff".to_string())
   }
+
+  #[test]
+  fn rgb_pads_components() {
+    assert_eq!(rgb((13, 0, 139)), "#0d008b".to_string())
+  }
+
+  #[test]
+  fn blend_0_returns_from() {
+    assert_eq!(
+      blend((13, 0, 139), (255, 255, 255), 0.0),
+      "#0d008b".to_string()
+    )
+  }
+
+  #[test]
+  fn blend_0_5_returns_middle() {
+    assert_eq!(blend((0, 0, 0), (255, 128, 10), 0.5), "#804005".to_string())
+  }
+
+  #[test]
+  fn blend_1_5_returns_to() {
+    assert_eq!(
+      blend((13, 0, 139), (255, 255, 255), 1.5),
+      "#ffffff".to_string()
+    )
+  }
 }
@@ -1,15 +1,78 @@
-use crate::color_utils::grey;
+use crate::color_utils::{blend, grey, rgb};
 use crate::life;
+use crate::life::hashlife::Block;
 use crate::settings::Settings;
 use lexicon::*;
+use std::collections::HashMap;
+use std::rc::Rc;
 use wasm_bindgen::*;
 use web_sys::WheelEvent;
 use yew::prelude::*;
 
+/// The number of colors cells are drawn with in the age and activity modes.
+const COLOR_STEPS: usize = 16;
+
+/// Cells that lived this long get the oldest color.
+const MAX_AGE: u64 = 100;
+
+/// How live cells are colored.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum RenderMode {
+  /// By state, with a trail of the previous generations.
+  #[default]
+  States,
+  /// By the number of generations they have been alive in a row.
+  Age,
+  /// Every cell by how often it was born or died, showing the rotors of
+  /// oscillators and where reactions happen.
+  Activity,
+}
+
+impl RenderMode {
+  pub const ALL: [RenderMode; 3] = [RenderMode::States, RenderMode::Age, RenderMode::Activity];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      RenderMode::States => "States",
+      RenderMode::Age => "Age",
+      RenderMode::Activity => "Activity",
+    }
+  }
+}
+
 #[derive(PartialEq, Properties)]
 pub struct BoardProps {
-  pub cells: life::CellSet,
+  pub cells: life::CellStates,
   pub previous_gens: Vec<life::CellSet>,
+  #[prop_or(2)]
+  pub states: u8,
+  /// The colors of the rule, for the states that have one.
+  #[prop_or_default]
+  pub colors: Vec<(u8, life::Rgb)>,
+  #[prop_or_default]
+  pub blocks: Vec<Block>,
+  #[prop_or_default]
+  pub topology: life::Topology,
+  #[prop_or_default]
+  pub show_copies: bool,
+  #[prop_or_default]
+  pub neighborhood: life::Neighborhood,
+  #[prop_or_default]
+  pub render_mode: RenderMode,
+  /// How long live cells have been alive, in the age mode.
+  #[prop_or_default]
+  pub ages: Rc<HashMap<Cell, u64>>,
+  /// How often cells were born or died, in the activity mode.
+  #[prop_or_default]
+  pub toggles: Rc<HashMap<Cell, u64>>,
+  #[prop_or_default]
+  pub max_toggles: u64,
+  /// The top left and bottom right cells of the selected area.
+  #[prop_or_default]
+  pub selection: Option<(Cell, Cell)>,
+  /// Called with the area the user drags over while holding shift.
+  #[prop_or_default]
+  pub on_select: Callback<Option<(Cell, Cell)>>,
   pub offset: (f64, f64),
   pub zoom: f64,
   pub move_offset: Callback<(f64, f64)>,
@@ -21,6 +84,81 @@ pub struct BoardProps {
 pub struct Board {
   canvas_ref: NodeRef,
   last_offset: Option<(f64, f64)>,
+  /// Where the selection being made started.
+  selection_start: Option<Cell>,
+}
+
+fn size_to_cells(settings: &Settings, size: f64, zoom: f64) -> f64 {
+  (size / (settings.cell_size * zoom + settings.grid_width) as f64).ceil()
+}
+
+/// The coordinates of the cells visible on a board of the given size. On a
+/// hexagonal grid, rows are shifted half a cell to the left of the one
+/// above, so more columns are visible.
+pub fn cell_range(
+  settings: &Settings,
+  (width, height): (u32, u32),
+  offset: (f64, f64),
+  zoom: f64,
+  hexagonal: bool,
+) -> (std::ops::Range<i32>, std::ops::Range<i32>) {
+  let from_x = size_to_cells(settings, -offset.0, zoom) as i32 - 1;
+  let to_x = from_x + size_to_cells(settings, width as f64, zoom) as i32 + 1;
+  let from_y = size_to_cells(settings, -offset.1, zoom) as i32 - 1;
+  let to_y = from_y + size_to_cells(settings, height as f64, zoom) as i32 + 1;
+
+  if hexagonal {
+    (
+      from_x + from_y.div_euclid(2) - 1..to_x + to_y.div_euclid(2) + 2,
+      from_y..to_y,
+    )
+  } else {
+    (from_x..to_x, from_y..to_y)
+  }
+}
+
+/// The position of the top-left corner of a cell on the board.
+fn cell_position(
+  settings: &Settings,
+  (x, y): (f64, f64),
+  offset: (f64, f64),
+  zoom: f64,
+  hexagonal: bool,
+) -> (f64, f64) {
+  let cell_width = zoom * settings.cell_size + settings.grid_width;
+  let x = if hexagonal { x - y / 2.0 } else { x };
+  (offset.0 + cell_width * x, offset.1 + cell_width * y)
+}
+
+/// The cell at a position on the board.
+fn cell_at(
+  settings: &Settings,
+  (x, y): (f64, f64),
+  offset: (f64, f64),
+  zoom: f64,
+  hexagonal: bool,
+) -> Cell {
+  let cell_width = zoom * settings.cell_size + settings.grid_width;
+  let y = ((y - offset.1) / cell_width).floor();
+  let x = (x - offset.0) / cell_width;
+  let x = if hexagonal { x + y / 2.0 } else { x };
+  Cell {
+    x: x.floor() as i32,
+    y: y as i32,
+  }
+}
+
+/// Adds a hexagon to the current path, pointing up and down out of the
+/// square of the given size, so that rows shifted by half a cell fit
+/// together.
+fn hexagon(context: &web_sys::CanvasRenderingContext2d, (x, y): (f64, f64), size: f64) {
+  context.move_to(x + size / 2.0, y - size / 6.0);
+  context.line_to(x + size, y + size / 6.0);
+  context.line_to(x + size, y + size * 5.0 / 6.0);
+  context.line_to(x + size / 2.0, y + size * 7.0 / 6.0);
+  context.line_to(x, y + size * 5.0 / 6.0);
+  context.line_to(x, y + size / 6.0);
+  context.close_path();
 }
 
 impl Board {
@@ -46,15 +184,16 @@ impl Board {
     settings: &Settings,
     offset: (f64, f64),
     zoom: f64,
+    hexagonal: bool,
   ) -> (std::ops::Range<i32>, std::ops::Range<i32>) {
     let canvas = self.canvas();
-
-    let from_x = self.size_to_cells(settings, -offset.0, zoom) as i32 - 1;
-    let to_x = from_x + self.size_to_cells(settings, canvas.width() as f64, zoom) as i32 + 1;
-    let from_y = self.size_to_cells(settings, -offset.1, zoom) as i32 - 1;
-    let to_y = from_y + self.size_to_cells(settings, canvas.height() as f64, zoom) as i32 + 1;
-
-    (from_x..to_x, from_y..to_y)
+    cell_range(
+      settings,
+      (canvas.width(), canvas.height()),
+      offset,
+      zoom,
+      hexagonal,
+    )
   }
 
   fn erase(&self) {
@@ -64,16 +203,15 @@ impl Board {
     context.fill_rect(0.0, 0.0, canvas.width().into(), canvas.height().into())
   }
 
-  fn size_to_cells(&self, settings: &Settings, size: f64, zoom: f64) -> f64 {
-    (size / (settings.cell_size * zoom + settings.grid_width) as f64).ceil()
-  }
-
-  fn draw_grid(&self, settings: &Settings, offset: (f64, f64), zoom: f64) {
+  fn draw_grid(&self, settings: &Settings, offset: (f64, f64), zoom: f64, hexagonal: bool) {
+    if hexagonal {
+      return self.draw_hexagonal_grid(settings, offset, zoom);
+    }
     let canvas = self.canvas();
     let context = self.context();
     context.set_fill_style(&JsValue::from_str(grey(0.9).as_str()));
 
-    let (cell_range_x, cell_range_y) = self.cell_range(settings, offset, zoom);
+    let (cell_range_x, cell_range_y) = self.cell_range(settings, offset, zoom, false);
     for i in cell_range_x {
       context.fill_rect(
         offset.0 + i as f64 * (zoom * settings.cell_size + settings.grid_width),
@@ -93,6 +231,25 @@ impl Board {
     }
   }
 
+  /// Outlines every visible cell, as hexagons don't line up into straight
+  /// lines.
+  fn draw_hexagonal_grid(&self, settings: &Settings, offset: (f64, f64), zoom: f64) {
+    let context = self.context();
+    context.set_stroke_style(&JsValue::from_str(grey(0.9).as_str()));
+    context.set_line_width(settings.grid_width);
+
+    let cell_width = zoom * settings.cell_size + settings.grid_width;
+    let (cell_range_x, cell_range_y) = self.cell_range(settings, offset, zoom, true);
+    context.begin_path();
+    for y in cell_range_y {
+      for x in cell_range_x.clone() {
+        let position = cell_position(settings, (x as f64, y as f64), offset, zoom, true);
+        hexagon(&context, position, cell_width);
+      }
+    }
+    context.stroke();
+  }
+
   fn draw_cells(
     &self,
     settings: &Settings,
@@ -100,11 +257,12 @@ impl Board {
     color: String,
     offset: (f64, f64),
     zoom: f64,
+    hexagonal: bool,
   ) {
     let context = self.context();
     context.set_fill_style(&JsValue::from(color));
 
-    let (cell_range_x, cell_range_y) = self.cell_range(settings, offset, zoom);
+    let (cell_range_x, cell_range_y) = self.cell_range(settings, offset, zoom, hexagonal);
     let cells = cells.iter().filter(|Cell { x, y }| {
       *x >= cell_range_x.start
         && *x <= cell_range_x.end
@@ -112,17 +270,200 @@ impl Board {
         && *y <= cell_range_y.end
     });
 
+    let size = zoom * settings.cell_size;
+    if hexagonal {
+      context.begin_path();
+    }
     for cell in cells {
-      context.fill_rect(
-        offset.0
-          + (settings.grid_width
-            + (zoom * settings.cell_size + settings.grid_width) * cell.x as f64),
-        offset.1
-          + (settings.grid_width
-            + (zoom * settings.cell_size + settings.grid_width) * cell.y as f64),
-        zoom * settings.cell_size,
-        zoom * settings.cell_size,
+      let (x, y) = cell_position(
+        settings,
+        (cell.x as f64, cell.y as f64),
+        offset,
+        zoom,
+        hexagonal,
       );
+      let (x, y) = (x + settings.grid_width, y + settings.grid_width);
+      if hexagonal {
+        hexagon(&context, (x, y), size);
+      } else {
+        context.fill_rect(x, y, size, size);
+      }
+    }
+    if hexagonal {
+      context.fill();
+    }
+  }
+
+  /// Adds the outline of an area of the universe to the current path, which
+  /// is a parallelogram on a hexagonal grid.
+  fn area(
+    &self,
+    settings: &Settings,
+    (x, y): (f64, f64),
+    (width, height): (f64, f64),
+    offset: (f64, f64),
+    zoom: f64,
+    hexagonal: bool,
+  ) {
+    let context = self.context();
+    let corner =
+      |dx: f64, dy: f64| cell_position(settings, (x + dx, y + dy), offset, zoom, hexagonal);
+    let corners = [
+      corner(0.0, 0.0),
+      corner(width, 0.0),
+      corner(width, height),
+      corner(0.0, height),
+    ];
+    context.move_to(corners[0].0, corners[0].1);
+    for (x, y) in &corners[1..] {
+      context.line_to(*x, *y);
+    }
+    context.close_path();
+  }
+
+  /// Draws the blocks with an opacity depending on their density, so that
+  /// sparse areas remain visible when zoomed out.
+  fn draw_blocks(
+    &self,
+    settings: &Settings,
+    blocks: &[Block],
+    offset: (f64, f64),
+    zoom: f64,
+    hexagonal: bool,
+  ) {
+    let context = self.context();
+    context.set_fill_style(&JsValue::from_str("#0d008b"));
+
+    let cell_width = zoom * settings.cell_size + settings.grid_width;
+    for block in blocks {
+      context.set_global_alpha(block.density.sqrt());
+      if hexagonal {
+        context.begin_path();
+        let size = block.size as f64;
+        let position = (block.x as f64, block.y as f64);
+        self.area(settings, position, (size, size), offset, zoom, true);
+        context.fill();
+      } else {
+        context.fill_rect(
+          offset.0 + (settings.grid_width + cell_width * block.x as f64),
+          offset.1 + (settings.grid_width + cell_width * block.y as f64),
+          cell_width * block.size as f64 - settings.grid_width,
+          cell_width * block.size as f64 - settings.grid_width,
+        );
+      }
+    }
+    context.set_global_alpha(1.0);
+  }
+
+  fn draw_selection(
+    &self,
+    settings: &Settings,
+    (top_left, bottom_right): (Cell, Cell),
+    offset: (f64, f64),
+    zoom: f64,
+    hexagonal: bool,
+  ) {
+    let context = self.context();
+    context.set_stroke_style(&JsValue::from_str("darkorange"));
+    context.set_line_width(2.0);
+
+    context.begin_path();
+    self.area(
+      settings,
+      (top_left.x as f64, top_left.y as f64),
+      (
+        (bottom_right.x - top_left.x + 1) as f64,
+        (bottom_right.y - top_left.y + 1) as f64,
+      ),
+      offset,
+      zoom,
+      hexagonal,
+    );
+    context.stroke();
+  }
+
+  fn draw_boundary(
+    &self,
+    settings: &Settings,
+    topology: &life::Topology,
+    offset: (f64, f64),
+    zoom: f64,
+    hexagonal: bool,
+  ) {
+    if let Some((top_left, width, height)) = topology.bounds() {
+      let context = self.context();
+      context.set_stroke_style(&JsValue::from_str("#0d008b"));
+      context.set_line_width(2.0);
+
+      context.begin_path();
+      self.area(
+        settings,
+        (top_left.x as f64, top_left.y as f64),
+        (width as f64, height as f64),
+        offset,
+        zoom,
+        hexagonal,
+      );
+      context.stroke();
+    }
+  }
+
+  /// Draws the eight copies of a torus universe around it, as if the plane
+  /// was tiled with it.
+  fn draw_torus_copies(
+    &self,
+    settings: &Settings,
+    cells: &life::CellSet,
+    topology: &life::Topology,
+    offset: (f64, f64),
+    zoom: f64,
+    hexagonal: bool,
+  ) {
+    if let life::Topology::Torus { width, height } = *topology {
+      for (i, j) in (-1..=1).flat_map(|i| (-1..=1).map(move |j| (i, j))) {
+        if i == 0 && j == 0 {
+          continue;
+        }
+        let copy = cells
+          .iter()
+          .map(|cell| Cell {
+            x: cell.x + i * width as i32,
+            y: cell.y + j * height as i32,
+          })
+          .collect();
+        self.draw_cells(
+          settings,
+          &copy,
+          "#9e99d1".to_string(),
+          offset,
+          zoom,
+          hexagonal,
+        );
+      }
+    }
+  }
+
+  /// Draws cells with a color between `from` and `to` depending on their
+  /// coefficient, batching them into a few colors.
+  fn draw_graded_cells(
+    &self,
+    settings: &Settings,
+    cells: impl Iterator<Item = (Cell, f64)>,
+    (from, to): ((u8, u8, u8), (u8, u8, u8)),
+    offset: (f64, f64),
+    zoom: f64,
+    hexagonal: bool,
+  ) {
+    let mut steps = vec![life::CellSet::new(); COLOR_STEPS];
+    for (cell, coeff) in cells {
+      let step = (coeff.clamp(0.0, 1.0) * (COLOR_STEPS - 1) as f64).round() as usize;
+      steps[step].insert(cell);
+    }
+    for (step, cells) in steps.iter().enumerate() {
+      if !cells.is_empty() {
+        let color = blend(from, to, step as f64 / (COLOR_STEPS - 1) as f64);
+        self.draw_cells(settings, cells, color, offset, zoom, hexagonal);
+      }
     }
   }
 
@@ -133,6 +474,44 @@ impl Board {
     crate::color_utils::grey(coeff)
   }
 
+  /// The color given by the rule, or else one fading away from the live
+  /// color as the state increases, like dying cells do.
+  fn color_for_state(&self, state: u8, states: u8, colors: &[(u8, life::Rgb)]) -> String {
+    match colors
+      .iter()
+      .find(|(colored_state, _)| *colored_state == state)
+    {
+      Some(&(_, color)) => rgb(color),
+      None => {
+        let coeff = (state - 1) as f64 / (states - 1) as f64;
+        blend((13, 0, 139), (220, 218, 240), coeff)
+      }
+    }
+  }
+
+  /// Selects the area between the cell the selection started from and the
+  /// one at a position on the board.
+  fn select(&self, ctx: &Context<Self>, start: Cell, (x, y): (i32, i32)) {
+    let hexagonal = ctx.props().neighborhood == life::Neighborhood::Hexagonal;
+    let end = cell_at(
+      &self.settings(ctx),
+      (x as f64, y as f64),
+      ctx.props().offset,
+      ctx.props().zoom,
+      hexagonal,
+    );
+    ctx.props().on_select.emit(Some((
+      Cell {
+        x: start.x.min(end.x),
+        y: start.y.min(end.y),
+      },
+      Cell {
+        x: start.x.max(end.x),
+        y: start.y.max(end.y),
+      },
+    )));
+  }
+
   fn settings(&self, ctx: &Context<Self>) -> Settings {
     ctx
       .link()
@@ -144,6 +523,7 @@ impl Board {
 
 pub enum BoardMessage {
   PointerDown(i32, i32),
+  SelectionStart(i32, i32),
   PointerUp(i32, i32),
   PointerMove(i32, i32),
   Zoom(i32, i32, f64),
@@ -157,6 +537,7 @@ impl Component for Board {
     Self {
       canvas_ref: NodeRef::default(),
       last_offset: None,
+      selection_start: None,
     }
   }
 
@@ -166,12 +547,29 @@ impl Component for Board {
         self.last_offset = Some((x as f64, y as f64));
         false
       }
+      BoardMessage::SelectionStart(x, y) => {
+        let hexagonal = ctx.props().neighborhood == life::Neighborhood::Hexagonal;
+        let start = cell_at(
+          &self.settings(ctx),
+          (x as f64, y as f64),
+          ctx.props().offset,
+          ctx.props().zoom,
+          hexagonal,
+        );
+        self.selection_start = Some(start);
+        self.select(ctx, start, (x, y));
+        false
+      }
       BoardMessage::PointerUp(_x, _y) => {
         self.last_offset = None;
+        self.selection_start = None;
         false
       }
       BoardMessage::PointerMove(x, y) => {
-        if let Some(last_offset) = self.last_offset {
+        if let Some(start) = self.selection_start {
+          self.select(ctx, start, (x, y));
+          false
+        } else if let Some(last_offset) = self.last_offset {
           let offset = ctx.props().offset;
           let new_offset = (
             offset.0 + x as f64 - last_offset.0,
@@ -190,7 +588,7 @@ impl Component for Board {
         }
       }
       BoardMessage::Zoom(x1, y1, zoom) => {
-        let zoom = f64::max(f64::min(ctx.props().zoom - 0.1 * zoom / 120.0, 5.0), 0.1);
+        let zoom = f64::max(f64::min(ctx.props().zoom - 0.1 * zoom / 120.0, 5.0), 0.02);
         ctx.props().change_zoom.emit((x1, y1, zoom));
         true
       }
@@ -204,11 +602,27 @@ impl Component for Board {
     let settings = self.settings(ctx);
     let zoom = ctx.props().zoom;
     let offset = ctx.props().offset;
+    let hexagonal = ctx.props().neighborhood == life::Neighborhood::Hexagonal;
     self.erase();
     if ctx.props().zoom > 0.3 {
-      self.draw_grid(&settings, offset, zoom);
+      self.draw_grid(&settings, offset, zoom, hexagonal);
     }
-    let previous_gens = &ctx.props().previous_gens;
+    if ctx.props().show_copies {
+      self.draw_torus_copies(
+        &settings,
+        &ctx.props().cells.keys().copied().collect(),
+        &ctx.props().topology,
+        offset,
+        zoom,
+        hexagonal,
+      );
+    }
+    let render_mode = ctx.props().render_mode;
+    let previous_gens: &[life::CellSet] = if render_mode == RenderMode::States {
+      &ctx.props().previous_gens
+    } else {
+      &[]
+    };
     let num_gens = previous_gens.len();
     for i in 0..num_gens {
       let gen_index = num_gens - i - 1;
@@ -218,15 +632,68 @@ impl Component for Board {
         self.color_for_previous_gen(gen_index, num_gens),
         offset,
         zoom,
+        hexagonal,
       );
     }
-    self.draw_cells(
-      &settings,
-      &ctx.props().cells,
-      "#0d008b".to_string(),
-      offset,
-      zoom,
-    );
+    let states = ctx.props().states;
+    let max_toggles = ctx.props().max_toggles.max(1) as f64;
+    if render_mode == RenderMode::Activity {
+      self.draw_graded_cells(
+        &settings,
+        ctx
+          .props()
+          .toggles
+          .iter()
+          .map(|(&cell, &toggles)| (cell, toggles as f64 / max_toggles)),
+        ((255, 230, 150), (200, 0, 0)),
+        offset,
+        zoom,
+        hexagonal,
+      );
+    }
+    for state in 1..states {
+      let cells = ctx
+        .props()
+        .cells
+        .iter()
+        .filter(|(_, &cell_state)| cell_state == state)
+        .map(|(&cell, _)| cell);
+      let cells = match render_mode {
+        RenderMode::Age if state == 1 => {
+          let ages = &ctx.props().ages;
+          self.draw_graded_cells(
+            &settings,
+            cells.map(|cell| {
+              let age = ages.get(&cell).copied().unwrap_or(1);
+              (cell, (age as f64).ln() / (MAX_AGE as f64).ln())
+            }),
+            ((255, 160, 0), (13, 0, 139)),
+            offset,
+            zoom,
+            hexagonal,
+          );
+          continue;
+        }
+        // Cells that changed are already drawn with their activity
+        RenderMode::Activity if state == 1 => cells
+          .filter(|cell| !ctx.props().toggles.contains_key(cell))
+          .collect(),
+        _ => cells.collect(),
+      };
+      self.draw_cells(
+        &settings,
+        &cells,
+        self.color_for_state(state, states, &ctx.props().colors),
+        offset,
+        zoom,
+        hexagonal,
+      );
+    }
+    self.draw_blocks(&settings, &ctx.props().blocks, offset, zoom, hexagonal);
+    self.draw_boundary(&settings, &ctx.props().topology, offset, zoom, hexagonal);
+    if let Some(selection) = ctx.props().selection {
+      self.draw_selection(&settings, selection, offset, zoom, hexagonal);
+    }
   }
 
   fn view(&self, ctx: &Context<Self>) -> Html {
@@ -236,10 +703,14 @@ impl Component for Board {
         class="board"
         width={ctx.props().width.to_string()}
         height={ctx.props().height.to_string()}
-        onpointerdown={ctx.link().callback(|event: PointerEvent| BoardMessage::PointerDown(event.client_x(), event.client_y()))}
-        onpointerup={ctx.link().callback(|event: PointerEvent| BoardMessage::PointerUp(event.client_x(), event.client_y()))}
-        onpointerout={ctx.link().callback(|event: PointerEvent| BoardMessage::PointerUp(event.client_x(), event.client_y()))}
-        onpointermove={ctx.link().callback(|event: PointerEvent| BoardMessage::PointerMove(event.client_x(), event.client_y()))}
+        onpointerdown={ctx.link().callback(|event: PointerEvent| if event.shift_key() {
+          BoardMessage::SelectionStart(event.offset_x(), event.offset_y())
+        } else {
+          BoardMessage::PointerDown(event.offset_x(), event.offset_y())
+        })}
+        onpointerup={ctx.link().callback(|event: PointerEvent| BoardMessage::PointerUp(event.offset_x(), event.offset_y()))}
+        onpointerout={ctx.link().callback(|event: PointerEvent| BoardMessage::PointerUp(event.offset_x(), event.offset_y()))}
+        onpointermove={ctx.link().callback(|event: PointerEvent| BoardMessage::PointerMove(event.offset_x(), event.offset_y()))}
         onwheel={ctx.link().callback(|event: WheelEvent| BoardMessage::Zoom(event.client_x(), event.client_y(), event.delta_y()))}
       />
     }
@@ -0,0 +1,68 @@
+use crate::life::{tally, Object, Periodicity};
+use std::collections::HashMap;
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct CensusTableProps {
+  pub objects: Vec<Object>,
+  /// The names of the objects, by apgcode.
+  #[prop_or_default]
+  pub names: HashMap<String, String>,
+}
+
+fn kind(periodicity: Periodicity) -> &'static str {
+  match periodicity {
+    Periodicity::StillLife => "still life",
+    Periodicity::Oscillator { .. } => "oscillator",
+    Periodicity::Spaceship { .. } => "spaceship",
+    _ => "unstable",
+  }
+}
+
+#[function_component(CensusTable)]
+pub fn census_table(props: &CensusTableProps) -> Html {
+  let objects = &props.objects;
+  let rows = tally(objects).into_iter().map(|(apgcode, count)| {
+    let periodicity = objects
+      .iter()
+      .find(|object| object.apgcode == apgcode)
+      .map_or(Periodicity::NotYetPeriodic, |object| object.periodicity);
+    let name = apgcode
+      .as_deref()
+      .and_then(|apgcode| props.names.get(apgcode).map(String::as_str));
+    html! {
+      <tr>
+        <td title={apgcode.clone()}>
+          {name.map_or(apgcode.clone().unwrap_or_else(|| "—".to_string()), str::to_string)}
+        </td>
+        <td>{kind(periodicity)}</td>
+        <td class="count">{count}</td>
+      </tr>
+    }
+  });
+  let totals = ["still life", "oscillator", "spaceship", "unstable"]
+    .iter()
+    .map(|&total_kind| {
+      let count = objects
+        .iter()
+        .filter(|object| kind(object.periodicity) == total_kind)
+        .count();
+      (total_kind, count)
+    })
+    .filter(|&(_, count)| count > 0)
+    .map(|(kind, count)| format!("{} {}", count, kind))
+    .collect::<Vec<_>>()
+    .join(" · ");
+
+  html! {
+    <div class="census">
+      <table>
+        <thead>
+          <tr><th>{"Object"}</th><th>{"Type"}</th><th class="count">{"Count"}</th></tr>
+        </thead>
+        <tbody>{for rows}</tbody>
+      </table>
+      <div class="census-totals">{totals}</div>
+    </div>
+  }
+}
@@ -0,0 +1,110 @@
+use crate::life::Record;
+use wasm_bindgen::{JsCast, JsValue};
+use yew::prelude::*;
+
+/// How many generations are visible on the chart before it scrolls.
+pub const CHART_GENERATIONS: usize = 200;
+
+const WIDTH: u32 = 280;
+const HEIGHT: u32 = 100;
+
+#[derive(Properties, PartialEq)]
+pub struct ChartProps {
+  /// The last generations, oldest first.
+  pub records: Vec<Record>,
+  pub on_export: Callback<()>,
+}
+
+pub struct Chart {
+  canvas_ref: NodeRef,
+}
+
+impl Chart {
+  fn context(&self) -> web_sys::CanvasRenderingContext2d {
+    self
+      .canvas_ref
+      .cast::<web_sys::HtmlCanvasElement>()
+      .unwrap()
+      .get_context("2d")
+      .unwrap()
+      .unwrap()
+      .dyn_into::<web_sys::CanvasRenderingContext2d>()
+      .unwrap()
+  }
+
+  /// Draws one series as a line, scaled so that `max` is at the top.
+  fn draw_series(&self, values: impl Iterator<Item = u64>, max: u64, color: &str) {
+    let context = self.context();
+    let step = WIDTH as f64 / (CHART_GENERATIONS - 1) as f64;
+    context.begin_path();
+    for (i, value) in values.enumerate() {
+      let (x, y) = (
+        i as f64 * step,
+        HEIGHT as f64 - 1.0 - value as f64 / max as f64 * (HEIGHT - 2) as f64,
+      );
+      if i == 0 {
+        context.move_to(x, y);
+      } else {
+        context.line_to(x, y);
+      }
+    }
+    context.set_stroke_style(&JsValue::from_str(color));
+    context.stroke();
+  }
+}
+
+impl Component for Chart {
+  type Message = ();
+  type Properties = ChartProps;
+
+  fn create(_ctx: &Context<Self>) -> Self {
+    Self {
+      canvas_ref: NodeRef::default(),
+    }
+  }
+
+  fn rendered(&mut self, ctx: &Context<Self>, _first_render: bool) {
+    let records = &ctx.props().records;
+    let context = self.context();
+    context.clear_rect(0.0, 0.0, WIDTH as f64, HEIGHT as f64);
+    let max = records
+      .iter()
+      .map(|record| record.population.max(record.births).max(record.deaths))
+      .max()
+      .unwrap_or(0)
+      .max(1);
+    self.draw_series(records.iter().map(|record| record.births), max, "seagreen");
+    self.draw_series(records.iter().map(|record| record.deaths), max, "crimson");
+    self.draw_series(
+      records.iter().map(|record| record.population),
+      max,
+      "#0d008b",
+    );
+  }
+
+  fn view(&self, ctx: &Context<Self>) -> Html {
+    let summary = ctx.props().records.last().map_or(String::new(), |record| {
+      format!(
+        "Population {} · {}×{} · +{} −{}",
+        record.population,
+        record.width(),
+        record.height(),
+        record.births,
+        record.deaths
+      )
+    });
+    html! {
+      <div class="chart">
+        <canvas
+          ref={self.canvas_ref.clone()}
+          width={WIDTH.to_string()}
+          height={HEIGHT.to_string()}
+        />
+        <div class="chart-footer">
+          <span class="chart-summary">{summary}</span>
+          <button onclick={ctx.props().on_export.reform(|_| ())}>{"CSV"}</button>
+        </div>
+      </div>
+    }
+  }
+}
@@ -0,0 +1,318 @@
+use crate::components::pattern_selector::term_from_cells;
+use crate::life::{collide, orientations, CellSet, Incoming, Outcome, Rule, MAX_LANE, MAX_TIMING};
+use lexicon::Term;
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+/// Collisions aren’t run for more lanes and timings than this at once.
+const MAX_COLLISIONS: usize = 1024;
+
+/// The names of the orientations, in the order of `orientations`.
+const ORIENTATIONS: [&str; 8] = [
+  "as is",
+  "rotated 90°",
+  "rotated 180°",
+  "rotated 270°",
+  "flipped horizontally",
+  "flipped vertically",
+  "flipped diagonally",
+  "flipped antidiagonally",
+];
+
+/// A spaceship sent against the first one, with the lanes and timings to
+/// try.
+#[derive(Clone)]
+struct Sender {
+  ship: usize,
+  orientation: usize,
+  lanes: (i32, i32),
+  timings: (u64, u64),
+}
+
+impl Sender {
+  fn collisions(&self) -> usize {
+    let lanes = (self.lanes.1 - self.lanes.0 + 1).max(0) as usize;
+    let timings = (self.timings.1 + 1).saturating_sub(self.timings.0) as usize;
+    lanes * timings
+  }
+}
+
+pub struct CollisionLab {
+  first: usize,
+  senders: Vec<Sender>,
+  outcomes: Option<Result<Vec<Outcome>, String>>,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+  pub rule: Rule,
+  /// The lexicon patterns that are spaceships, by name.
+  pub ships: Rc<Vec<(String, CellSet)>>,
+  pub on_apply_pattern: Callback<Term>,
+}
+
+pub enum Msg {
+  ChangeFirst(usize),
+  ChangeShip(usize, usize),
+  ChangeOrientation(usize, usize),
+  ChangeLaneMin(usize, i32),
+  ChangeLaneMax(usize, i32),
+  ChangeTimingMin(usize, u64),
+  ChangeTimingMax(usize, u64),
+  AddSender,
+  RemoveSender(usize),
+  Run,
+  Load(usize),
+}
+
+impl CollisionLab {
+  fn run(&self, ships: &[(String, CellSet)], rule: &Rule) -> Result<Vec<Outcome>, String> {
+    let first = &ships.get(self.first).ok_or("No spaceship found")?.1;
+    let count = self
+      .senders
+      .iter()
+      .try_fold(1_usize, |count, sender| {
+        count.checked_mul(sender.collisions())
+      })
+      .unwrap_or(usize::MAX);
+    if count == 0 {
+      return Err("The ranges are empty".to_string());
+    }
+    if count > MAX_COLLISIONS {
+      return Err(format!(
+        "{} collisions is too many, try at most {}",
+        count, MAX_COLLISIONS
+      ));
+    }
+    let incoming: Vec<Incoming> = self
+      .senders
+      .iter()
+      .map(|sender| Incoming {
+        cells: orientations(&ships[sender.ship].1)[sender.orientation].clone(),
+        lanes: sender.lanes.0..=sender.lanes.1,
+        timings: sender.timings.0..=sender.timings.1,
+      })
+      .collect();
+    collide(first, &incoming, rule)
+  }
+}
+
+impl Component for CollisionLab {
+  type Message = Msg;
+  type Properties = Props;
+
+  fn create(ctx: &Context<Self>) -> Self {
+    // Two gliders crashing head-on, if there is one
+    let glider = ctx
+      .props()
+      .ships
+      .iter()
+      .position(|(name, _)| name == "glider")
+      .unwrap_or(0);
+    Self {
+      first: glider,
+      senders: vec![Sender {
+        ship: glider,
+        orientation: 2,
+        lanes: (-8, 8),
+        timings: (0, 3),
+      }],
+      outcomes: None,
+    }
+  }
+
+  fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+    let clamp_lane = |lane: i32| lane.clamp(-MAX_LANE, MAX_LANE);
+    match msg {
+      Msg::ChangeFirst(first) => self.first = first,
+      Msg::ChangeShip(i, ship) => self.senders[i].ship = ship,
+      Msg::ChangeOrientation(i, orientation) => self.senders[i].orientation = orientation,
+      Msg::ChangeLaneMin(i, lane) => self.senders[i].lanes.0 = clamp_lane(lane),
+      Msg::ChangeLaneMax(i, lane) => self.senders[i].lanes.1 = clamp_lane(lane),
+      Msg::ChangeTimingMin(i, timing) => self.senders[i].timings.0 = timing.min(MAX_TIMING),
+      Msg::ChangeTimingMax(i, timing) => self.senders[i].timings.1 = timing.min(MAX_TIMING),
+      Msg::AddSender => {
+        let sender = self.senders.last().unwrap().clone();
+        self.senders.push(Sender {
+          lanes: (0, 0),
+          timings: (0, 0),
+          ..sender
+        });
+      }
+      Msg::RemoveSender(i) => {
+        self.senders.remove(i);
+      }
+      Msg::Run => self.outcomes = Some(self.run(&ctx.props().ships, &ctx.props().rule)),
+      Msg::Load(index) => {
+        if let Some(Ok(outcomes)) = &self.outcomes {
+          let outcome = &outcomes[index];
+          let ships = &ctx.props().ships;
+          let senders: Vec<&str> = self
+            .senders
+            .iter()
+            .map(|sender| ships[sender.ship].0.as_str())
+            .collect();
+          let (lanes, timings) = describe(outcome);
+          ctx.props().on_apply_pattern.emit(term_from_cells(
+            format!("{} vs {}", ships[self.first].0, senders.join(", ")),
+            format!(
+              "Lanes {}, timings {}: {}",
+              lanes,
+              timings,
+              outcome.summary()
+            ),
+            "collision",
+            &outcome.start,
+          ));
+        }
+        return false;
+      }
+    }
+    true
+  }
+
+  fn view(&self, ctx: &Context<Self>) -> yew::virtual_dom::VNode {
+    let input_value = |event: Event| {
+      event
+        .target()
+        .and_then(|t| t.dyn_into::<HtmlInputElement>().ok())
+        .unwrap()
+        .value()
+    };
+    let select_value = |event: Event| -> usize {
+      event
+        .target()
+        .and_then(|t| t.dyn_into::<HtmlSelectElement>().ok())
+        .unwrap()
+        .value()
+        .parse()
+        .unwrap()
+    };
+
+    let ship_options = |selected: usize| {
+      html! {
+        <>
+          {for ctx.props().ships.iter().enumerate().map(|(i, (name, _))| html! {
+            <option value={i.to_string()} selected={selected == i}>{name}</option>
+          })}
+        </>
+      }
+    };
+
+    let number_input = |value: String, on_change: Callback<Event>| {
+      html! {
+        <input type="number" value={value} onchange={on_change}/>
+      }
+    };
+
+    let sender_view = |(i, sender): (usize, &Sender)| {
+      html! {
+        <>
+          <label>
+            <span>{"Against"}</span>
+            <select onchange={ctx.link().callback(move |event| Msg::ChangeShip(i, select_value(event)))}>
+              {ship_options(sender.ship)}
+            </select>
+            <select onchange={ctx.link().callback(move |event| Msg::ChangeOrientation(i, select_value(event)))}>
+              {for ORIENTATIONS.iter().enumerate().map(|(j, name)| html! {
+                <option value={j.to_string()} selected={sender.orientation == j}>{name}</option>
+              })}
+            </select>
+            <button
+              disabled={self.senders.len() == 1}
+              onclick={ctx.link().callback(move |_| Msg::RemoveSender(i))}
+            >{"Remove"}</button>
+          </label>
+          <label>
+            <span>{"Lanes"}</span>
+            {number_input(
+              sender.lanes.0.to_string(),
+              ctx.link().batch_callback(move |event| input_value(event).parse().ok().map(|lane| Msg::ChangeLaneMin(i, lane))),
+            )}
+            {number_input(
+              sender.lanes.1.to_string(),
+              ctx.link().batch_callback(move |event| input_value(event).parse().ok().map(|lane| Msg::ChangeLaneMax(i, lane))),
+            )}
+          </label>
+          <label>
+            <span>{"Timings"}</span>
+            {number_input(
+              sender.timings.0.to_string(),
+              ctx.link().batch_callback(move |event| input_value(event).parse().ok().map(|timing| Msg::ChangeTimingMin(i, timing))),
+            )}
+            {number_input(
+              sender.timings.1.to_string(),
+              ctx.link().batch_callback(move |event| input_value(event).parse().ok().map(|timing| Msg::ChangeTimingMax(i, timing))),
+            )}
+          </label>
+        </>
+      }
+    };
+
+    html! {
+      <div class="collision-lab">
+        <label>
+          <span>{"Spaceship"}</span>
+          <select onchange={ctx.link().callback(move |event| Msg::ChangeFirst(select_value(event)))}>
+            {ship_options(self.first)}
+          </select>
+        </label>
+        {for self.senders.iter().enumerate().map(sender_view)}
+        <button onclick={ctx.link().callback(|_| Msg::AddSender)}>{"Add a spaceship"}</button>
+        <button
+          disabled={ctx.props().ships.is_empty()}
+          onclick={ctx.link().callback(|_| Msg::Run)}
+        >{"Run"}</button>
+        {match &self.outcomes {
+          Some(Ok(outcomes)) => html! {
+            <table class="collisions">
+              <thead>
+                <tr><th>{"Lanes"}</th><th>{"Timings"}</th><th>{"Outcome"}</th></tr>
+              </thead>
+              <tbody>
+                {for outcomes.iter().enumerate().map(|(i, outcome)| {
+                  let (lanes, timings) = describe(outcome);
+                  html! {
+                    <tr
+                      title="Load this collision"
+                      onclick={ctx.link().callback(move |_| Msg::Load(i))}
+                    >
+                      <td class="count">{lanes}</td>
+                      <td class="count">{timings}</td>
+                      <td>{outcome.summary()}</td>
+                    </tr>
+                  }
+                })}
+              </tbody>
+            </table>
+          },
+          Some(Err(error)) => html! { <div class="identification">{error}</div> },
+          None => html! {},
+        }}
+      </div>
+    }
+  }
+}
+
+/// The lanes and timings of the incoming spaceships of a collision.
+fn describe(outcome: &Outcome) -> (String, String) {
+  let join = |values: Vec<String>| values.join(", ");
+  (
+    join(
+      outcome
+        .setups
+        .iter()
+        .map(|setup| setup.lane.to_string())
+        .collect(),
+    ),
+    join(
+      outcome
+        .setups
+        .iter()
+        .map(|setup| setup.timing.to_string())
+        .collect(),
+    ),
+  )
+}
@@ -1,27 +1,78 @@
-use crate::components::board::Board;
+use crate::components::board::{cell_range, Board, RenderMode};
+use crate::components::census_table::CensusTable;
+use crate::components::chart::{Chart, CHART_GENERATIONS};
+use crate::components::collision_lab::CollisionLab;
+use crate::components::pattern_file::PatternFile;
 use crate::components::pattern_selector::PatternSelector;
+use crate::components::rule_picker::RulePicker;
+use crate::components::soup_generator::SoupGenerator;
 use crate::life::*;
 use crate::Settings;
 use gloo::events::EventListener;
+use gloo::file::{Blob, ObjectUrl};
 use gloo::timers::callback::Interval;
-use lexicon::Term;
+use lexicon::{Cell, Lexicon, Term};
+use std::collections::HashMap;
 use std::collections::VecDeque;
+use std::rc::Rc;
 use wasm_bindgen::JsCast;
-use web_sys::HtmlInputElement;
+use web_sys::{HtmlDocument, HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement};
 use yew::prelude::*;
 
+/// How far from the pattern the cells of its predecessor can be.
+const PREDECESSOR_MARGIN: i32 = 1;
+
+/// How often, in generations, to check whether the pattern has stabilised.
+const STABILISATION_INTERVAL: u64 = 100;
+
+/// Past this population, the cells aren’t watched every generation, so that
+/// huge patterns never have to be expanded.
+const MAX_TRACKED_POPULATION: u64 = 100_000;
+
+/// How long the predecessor search can go on before giving up, short enough
+/// not to freeze the page.
+const PREDECESSOR_MAX_CONFLICTS: u64 = 10_000;
+
+/// Why a board can’t be gone through cell by cell.
+fn too_many_cells() -> String {
+  format!(
+    "Too many cells, this only works up to {}",
+    MAX_TRACKED_POPULATION
+  )
+}
+
 pub struct Game {
-  cells: CellSet,
+  engine: Box<dyn LifeEngine>,
+  backend: Engine,
+  rule: Rule,
   previous_gens: Vec<CellSet>,
-  tick: u32,
+  period_detector: PeriodDetector,
+  stabilisation_detector: StabilisationDetector,
+  auto_pause: bool,
+  history: History,
+  activity: Activity,
+  render_mode: RenderMode,
+  download_url: Option<ObjectUrl>,
+  selection: Option<(Cell, Cell)>,
+  census: Option<Vec<Object>>,
+  census_names: HashMap<String, String>,
+  lexicon_index: Option<LexiconIndex>,
+  identification: Option<String>,
+  predecessor_search: Option<String>,
+  show_collision_lab: bool,
+  tick: u64,
   interval: Option<Interval>,
   speed: u8,
+  jump: u8,
+  show_copies: bool,
+  follow: bool,
   adjust_offset: Option<(usize, usize)>,
   offset: (f64, f64),
   zoom: f64,
   width: u32,
   height: u32,
   _resize_handle: EventListener,
+  _hash_change_handle: EventListener,
 }
 
 pub enum Msg {
@@ -29,7 +80,28 @@ pub enum Msg {
   Play,
   Pause,
   ChangeSpeed(u8),
+  ChangeJump(u8),
+  Jump,
   ApplyPattern(Term),
+  LoadPattern(Pattern),
+  LoadMacrocell(Macrocell),
+  Select(Option<(Cell, Cell)>),
+  ExportPattern(Format),
+  ChangeRule(Rule),
+  ChangeEngine(Engine),
+  ChangeRenderMode(RenderMode),
+  ToggleCopies,
+  ToggleFollow,
+  ToggleAutoPause,
+  ExportCsv,
+  TakeCensus,
+  Identify,
+  CopyApgcode,
+  Share,
+  OpenLink,
+  FindPredecessor,
+  CloseCensus,
+  ToggleCollisionLab,
   MoveOffset((f64, f64)),
   ChangeZoom((i32, i32, f64)),
   Resize,
@@ -51,6 +123,276 @@ impl Game {
     let interval = Interval::new(millis as u32, move || link.send_message(Msg::NextTick));
     self.interval = Some(interval);
   }
+
+  /// The area visible on the board, and the level of the blocks to draw it
+  /// with: as big as possible while still being a couple of pixels wide, or
+  /// 0 to draw every cell.
+  fn visible_area(&self, settings: &Settings) -> (Area, u8) {
+    let area = cell_range(
+      settings,
+      (self.width, self.height),
+      self.offset,
+      self.zoom,
+      self.rule.neighborhood() == Neighborhood::Hexagonal,
+    );
+    let cell_width = self.zoom * settings.cell_size + settings.grid_width;
+    let level = f64::max((2.0 / cell_width).log2().ceil(), 0.0) as u8;
+    (area, level)
+  }
+
+  /// The lexicon index, built the first time it is needed.
+  fn lexicon_index(&mut self) -> &LexiconIndex {
+    self
+      .lexicon_index
+      .get_or_insert_with(|| LexiconIndex::new(&Lexicon::get().terms))
+  }
+
+  /// The names of the objects, by apgcode. The lexicon only has patterns of
+  /// Conway’s Life.
+  fn names(&mut self, objects: &[Object]) -> HashMap<String, String> {
+    let index = if self.rule.is_conway() {
+      Some(&*self.lexicon_index())
+    } else {
+      None
+    };
+    objects
+      .iter()
+      .filter_map(|object| object.apgcode.as_deref())
+      .filter_map(|apgcode| {
+        let name = match index {
+          Some(index) => index.name(apgcode),
+          None => common_name(apgcode),
+        };
+        Some((apgcode.to_string(), name?.to_string()))
+      })
+      .collect()
+  }
+
+  /// Moves the view along with a spaceship that advanced by some
+  /// generations.
+  fn follow_spaceship(&mut self, settings: &Settings, generations: u64) {
+    if let Some((dx, dy)) = self.period_detector.periodicity().velocity() {
+      if self.follow {
+        let cell_width = self.zoom * settings.cell_size + settings.grid_width;
+        let (dx, dy) = (dx * generations as f64, dy * generations as f64);
+        let dx = if self.rule.neighborhood() == Neighborhood::Hexagonal {
+          dx - dy / 2.0
+        } else {
+          dx
+        };
+        self.offset = (
+          self.offset.0 - dx * cell_width,
+          self.offset.1 - dy * cell_width,
+        );
+      }
+    }
+  }
+
+  /// Replaces the board with some cells, centered in the view. Bounded
+  /// universes are centered on the origin, so is the pattern.
+  fn load(&mut self, settings: &Settings, cells: CellStates) {
+    let origin = Cell { x: 0, y: 0 };
+    let (top_left, bottom_right) =
+      engine::bounding_box(cells.keys().copied()).unwrap_or((origin, origin));
+    let (width, height) = (
+      bottom_right.x - top_left.x + 1,
+      bottom_right.y - top_left.y + 1,
+    );
+    let (left, top) = if self.rule.topology.is_bounded() {
+      (-width / 2, -height / 2)
+    } else {
+      (top_left.x, top_left.y)
+    };
+    let cells: CellStates = cells
+      .into_iter()
+      .map(|(Cell { x, y }, state)| {
+        let cell = Cell {
+          x: x - top_left.x + left,
+          y: y - top_left.y + top,
+        };
+        (cell, state)
+      })
+      .filter(|&(cell, state)| state < self.rule.states && self.rule.topology.contains(cell))
+      .collect();
+    self.engine = self.backend.create(&self.rule, &cells);
+    self.restart(settings);
+  }
+
+  /// Starts over from the cells of the engine, centered in the view.
+  fn restart(&mut self, settings: &Settings) {
+    self.start_at(0);
+    let origin = Cell { x: 0, y: 0 };
+    let (top_left, bottom_right) = self.engine.bounding_box().unwrap_or((origin, origin));
+    let (left, top) = (top_left.x, top_left.y);
+    let (width, height) = (
+      bottom_right.x - top_left.x + 1,
+      bottom_right.y - top_left.y + 1,
+    );
+    self.offset = (
+      (self.width as f64 / 2_f64
+        - (left as f64 + width as f64 / 2_f64)
+          * self.zoom
+          * (settings.cell_size + settings.grid_width) as f64),
+      (self.height as f64 / 2_f64
+        - (top as f64 + height as f64 / 2_f64)
+          * self.zoom
+          * (settings.cell_size + settings.grid_width) as f64),
+    );
+  }
+
+  /// Forgets everything about the previous cells, the ones of the engine
+  /// being at some generation.
+  fn start_at(&mut self, generation: u64) {
+    self.tick = generation;
+    self.previous_gens = vec![];
+    self.restart_period_detection();
+    self.history = History::new();
+    self.activity = Activity::new();
+    if let Some(cells) = self.tracked_cells() {
+      self.history.record(generation, &cells);
+      self.activity.record(&cells);
+    }
+    self.census = None;
+    self.identification = None;
+    self.predecessor_search = None;
+    self.selection = None;
+  }
+
+  /// The board as it can be shared in a link, unless it has too many cells.
+  fn snapshot(&self) -> Option<Snapshot> {
+    Some(Snapshot {
+      rule: self.rule.clone(),
+      generation: self.tick,
+      zoom: self.zoom,
+      offset: self.offset,
+      cells: self.tracked_cells()?,
+    })
+  }
+
+  /// Shows the board of a link, as it was when shared.
+  fn restore(&mut self, snapshot: Snapshot) {
+    if !self.backend.supports(&snapshot.rule) {
+      self.backend = Engine::best_for(&snapshot.rule);
+    }
+    self.rule = snapshot.rule;
+    let mut cells = snapshot.cells;
+    cells.retain(|&cell, &mut state| state < self.rule.states && self.rule.topology.contains(cell));
+    self.engine = self.backend.create(&self.rule, &cells);
+    self.start_at(snapshot.generation);
+    self.zoom = snapshot.zoom;
+    self.offset = snapshot.offset;
+  }
+
+  /// Restores the board of the link the page was opened with, if any.
+  fn restore_link(&mut self) {
+    let hash = web_sys::window().unwrap().location().hash().unwrap();
+    if hash.len() > 1 {
+      match Snapshot::from_fragment(&hash) {
+        Ok(snapshot) => self.restore(snapshot),
+        Err(error) => self.identification = Some(format!("Invalid link: {}", error)),
+      }
+    }
+  }
+
+  /// The cells, unless there are too many of them to watch every
+  /// generation.
+  fn tracked_cells(&self) -> Option<CellStates> {
+    (self.engine.population() <= MAX_TRACKED_POPULATION).then(|| self.engine.cells())
+  }
+
+  /// The cells of the selection if there is one, of the board otherwise,
+  /// unless the board has too many cells to go through.
+  fn selected_cells(&self) -> Option<CellStates> {
+    let mut cells = self.tracked_cells()?;
+    if let Some((top_left, bottom_right)) = self.selection {
+      cells.retain(|cell, _| {
+        (top_left.x..=bottom_right.x).contains(&cell.x)
+          && (top_left.y..=bottom_right.y).contains(&cell.y)
+      });
+    }
+    Some(cells)
+  }
+
+  /// Copies some text to the clipboard.
+  fn copy_to_clipboard(&self, text: &str) {
+    let document = web_sys::window().unwrap().document().unwrap();
+    let input = document
+      .create_element("textarea")
+      .unwrap()
+      .dyn_into::<HtmlTextAreaElement>()
+      .unwrap();
+    input.set_value(text);
+    document.body().unwrap().append_child(&input).unwrap();
+    input.select();
+    // The asynchronous clipboard API is still unstable in web-sys
+    document
+      .dyn_into::<HtmlDocument>()
+      .unwrap()
+      .exec_command("copy")
+      .unwrap();
+    input.remove();
+  }
+
+  /// Makes the browser download a file.
+  fn download(&mut self, contents: &str, mime_type: &str, file_name: &str) {
+    let blob = Blob::new_with_options(contents, Some(mime_type));
+    let url = ObjectUrl::from(blob);
+    let document = web_sys::window().unwrap().document().unwrap();
+    let link = document
+      .create_element("a")
+      .unwrap()
+      .dyn_into::<web_sys::HtmlElement>()
+      .unwrap();
+    link.set_attribute("href", &url).unwrap();
+    link.set_attribute("download", file_name).unwrap();
+    link.click();
+    // The download needs the URL to stay valid a bit after the click
+    self.download_url = Some(url);
+  }
+
+  /// Forgets the previous generations, when the next ones won’t follow from
+  /// them.
+  fn restart_period_detection(&mut self) {
+    self.period_detector = PeriodDetector::new();
+    self.stabilisation_detector = StabilisationDetector::new();
+    if let Some(cells) = self.tracked_cells() {
+      self.period_detector.record(self.tick, &cells);
+      self.record_stabilisation(&cells);
+    }
+  }
+
+  /// Records the generation, checking every now and then whether the
+  /// pattern has become ash, to pause if asked to.
+  fn record_stabilisation(&mut self, cells: &CellStates) {
+    if !self.rule.is_life_like() {
+      return;
+    }
+    let cells = alive_cells(cells);
+    self.stabilisation_detector.record(self.tick, &cells);
+    if self.tick % STABILISATION_INTERVAL == 0
+      && self.stabilisation_detector.stabilisation().is_none()
+      && self
+        .stabilisation_detector
+        .check(&cells, &self.rule)
+        .is_some()
+      && self.auto_pause
+    {
+      self.interval = None;
+    }
+  }
+
+  /// Moves the cells to another engine, dropping the ones the rule can’t
+  /// have. The engine is left as it is if there are too many cells to move.
+  fn replace_engine(&mut self, backend: Engine, rule: &Rule) -> bool {
+    let mut cells = match self.tracked_cells() {
+      Some(cells) => cells,
+      None => return false,
+    };
+    cells.retain(|&cell, &mut state| state < rule.states && rule.topology.contains(cell));
+    self.engine = backend.create(rule, &cells);
+    self.backend = backend;
+    true
+  }
 }
 
 impl Component for Game {
@@ -64,13 +406,17 @@ impl Component for Game {
         self.tick += 1;
         self.adjust_offset = None;
 
-        self.previous_gens = {
+        let (area, level) = self.visible_area(&settings);
+        self.previous_gens = if level > 0 {
+          vec![]
+        } else {
           let mut previous_gens_deque: VecDeque<CellSet> = self
             .previous_gens
             .iter()
             .map(|cell_set| cell_set.clone())
             .collect();
-          previous_gens_deque.push_front(self.cells.clone());
+          previous_gens_deque
+            .push_front(self.engine.viewport(&area).map(|(cell, _)| cell).collect());
           if previous_gens_deque.len() > settings.num_previous {
             previous_gens_deque.pop_back();
           }
@@ -80,8 +426,15 @@ impl Component for Game {
             .collect()
         };
 
-        self.cells = tick(&self.cells);
-
+        self.engine.step();
+        self.identification = None;
+        if let Some(cells) = self.tracked_cells() {
+          self.period_detector.record(self.tick, &cells);
+          self.record_stabilisation(&cells);
+          self.history.record(self.tick, &cells);
+          self.activity.record(&cells);
+        }
+        self.follow_spaceship(&settings, 1);
         true
       }
       Msg::Play => {
@@ -99,21 +452,287 @@ impl Component for Game {
         }
         true
       }
-      Msg::ApplyPattern(term) => {
-        self.cells = term
-          .cells
-          .iter()
-          .fold(CellSet::new(), |cells, &cell| make_cell_alive(&cells, cell));
-        self.tick = 0;
+      Msg::ChangeJump(jump) => {
+        self.jump = jump;
+        false
+      }
+      Msg::Jump => {
+        // Only the quadtree jumps without computing every generation
+        if self.backend != Engine::HashLife && Engine::HashLife.supports(&self.rule) {
+          let rule = self.rule.clone();
+          if !self.replace_engine(Engine::HashLife, &rule) {
+            self.identification = Some(too_many_cells());
+            return true;
+          }
+        }
+        self.engine.step_n(1 << self.jump);
+        self.follow_spaceship(&settings, 1 << self.jump);
         self.previous_gens = vec![];
-        self.offset = (
-          (self.width as f64 / 2_f64
-            - term.width as f64 * self.zoom * (settings.cell_size + settings.grid_width) as f64
-              / 2_f64),
-          (self.height as f64 / 2_f64
-            - term.height as f64 * self.zoom * (settings.cell_size + settings.grid_width) as f64
-              / 2_f64),
+        self.tick += 1_u64 << self.jump;
+        self.identification = None;
+        self.restart_period_detection();
+        // Cells weren't watched during the jump
+        self.activity = Activity::new();
+        if let Some(cells) = self.tracked_cells() {
+          self.history.record(self.tick, &cells);
+          self.activity.record(&cells);
+        }
+        true
+      }
+      Msg::ApplyPattern(term) => {
+        let cells = term.cells.iter().map(|&cell| (cell, 1)).collect();
+        self.load(&settings, cells);
+        true
+      }
+      Msg::LoadPattern(pattern) => {
+        if let Some(Ok(rule)) = pattern.rule.as_deref().map(str::parse::<Rule>) {
+          if rule != self.rule {
+            self.update(ctx, Msg::ChangeRule(rule));
+          }
+        }
+        self.load(&settings, pattern.cells);
+        true
+      }
+      Msg::LoadMacrocell(macrocell) => {
+        let rule = match macrocell.rule.as_deref().map(str::parse::<Rule>) {
+          Some(Ok(rule)) => rule,
+          _ => self.rule.clone(),
+        };
+        // Only the quadtree runs the pattern without expanding it
+        if !Engine::HashLife.supports(&rule) {
+          self.identification = Some(format!(
+            "Macrocell patterns can’t be run in {}, which HashLife doesn’t support",
+            rule
+          ));
+          return true;
+        }
+        if rule != self.rule {
+          self.update(ctx, Msg::ChangeRule(rule));
+        }
+        let mut universe = macrocell.universe;
+        universe.set_rule(&self.rule);
+        self.engine = Box::new(universe);
+        self.backend = Engine::HashLife;
+        self.restart(&settings);
+        true
+      }
+      Msg::Select(selection) => {
+        self.selection = selection;
+        true
+      }
+      Msg::ExportPattern(format) => {
+        let comment = format!("Generation {}", self.tick);
+        let file_name = format!("pattern.{}", format.extension());
+        // Whole quadtrees are written without expanding them
+        if let (Format::Macrocell, None, Some(universe)) =
+          (format, self.selection, self.engine.quadtree())
+        {
+          let contents = universe.to_macrocell(&[comment]);
+          self.download(&contents, "text/plain", &file_name);
+          return false;
+        }
+        let cells = match self.selected_cells() {
+          Some(cells) => cells,
+          None => {
+            self.identification = Some(too_many_cells());
+            return true;
+          }
+        };
+        let mut pattern = Pattern::new(cells);
+        pattern.rule = Some(self.rule.to_string());
+        pattern.comments = vec![comment];
+        self.download(&pattern.write(format), "text/plain", &file_name);
+        false
+      }
+      Msg::ChangeRule(rule) => {
+        let backend = if self.backend.supports(&rule) {
+          self.backend
+        } else {
+          Engine::best_for(&rule)
+        };
+        if backend != self.backend || rule.topology.is_bounded() || rule.states < self.rule.states {
+          if !self.replace_engine(backend, &rule) {
+            self.identification = Some(too_many_cells());
+            return true;
+          }
+        } else {
+          self.engine.set_rule(&rule);
+        }
+        self.rule = rule;
+        self.restart_period_detection();
+        self.identification = None;
+        self.predecessor_search = None;
+        true
+      }
+      Msg::ChangeEngine(backend) => {
+        let rule = self.rule.clone();
+        if !self.replace_engine(backend, &rule) {
+          self.identification = Some(too_many_cells());
+        }
+        true
+      }
+      Msg::ChangeRenderMode(render_mode) => {
+        self.render_mode = render_mode;
+        true
+      }
+      Msg::ToggleCopies => {
+        self.show_copies = !self.show_copies;
+        true
+      }
+      Msg::ToggleFollow => {
+        self.follow = !self.follow;
+        true
+      }
+      Msg::ToggleAutoPause => {
+        self.auto_pause = !self.auto_pause;
+        true
+      }
+      Msg::ExportCsv => {
+        self.download(&self.history.to_csv(), "text/csv", "history.csv");
+        false
+      }
+      Msg::TakeCensus => {
+        let cells = match self.tracked_cells() {
+          Some(cells) => cells,
+          None => {
+            self.identification = Some(too_many_cells());
+            return true;
+          }
+        };
+        let objects = census(&alive_cells(&cells), &self.rule);
+        self.census_names = self.names(&objects);
+        self.census = Some(objects);
+        true
+      }
+      Msg::Identify => {
+        let cells = match self.selected_cells() {
+          Some(cells) => alive_cells(&cells),
+          None => {
+            self.identification = Some(too_many_cells());
+            return true;
+          }
+        };
+        let identification = if self.selection.is_some() {
+          let form = canonical_form(&cells, &self.rule);
+          match self.lexicon_index().names_of(&form) {
+            Some(names) => format!("This is a ‘{}’", names.join("’, ‘")),
+            None => format!("Unknown pattern ({})", form),
+          }
+        } else {
+          // The board is named object by object, once it has settled down
+          let objects = census(&cells, &self.rule);
+          let names = self.names(&objects);
+          let parts: Vec<String> = tally(&objects)
+            .into_iter()
+            .map(|(apgcode, count)| {
+              let name = match apgcode {
+                Some(apgcode) => names.get(&apgcode).cloned().unwrap_or(apgcode),
+                None => "unstable".to_string(),
+              };
+              format!("{} ‘{}’", count, name)
+            })
+            .collect();
+          if parts.is_empty() {
+            "The board is empty".to_string()
+          } else {
+            format!("This is {}", parts.join(", "))
+          }
+        };
+        self.identification = Some(identification);
+        true
+      }
+      Msg::CopyApgcode => {
+        let cells = match self.selected_cells() {
+          Some(cells) => alive_cells(&cells),
+          None => {
+            self.identification = Some(too_many_cells());
+            return true;
+          }
+        };
+        let message = match apgcode_of(&cells, &self.rule) {
+          Some(apgcode) => {
+            self.copy_to_clipboard(&apgcode);
+            format!("Copied {}", apgcode)
+          }
+          None => "Not a still life, oscillator or spaceship".to_string(),
+        };
+        self.identification = Some(message);
+        true
+      }
+      Msg::FindPredecessor => {
+        let selected = match self.selected_cells() {
+          Some(cells) => cells,
+          None => {
+            self.predecessor_search = Some(too_many_cells());
+            return true;
+          }
+        };
+        let result = predecessor(
+          &alive_cells(&selected),
+          &self.rule,
+          PREDECESSOR_MARGIN,
+          PREDECESSOR_MAX_CONFLICTS,
         );
+        self.predecessor_search = Some(match result {
+          Predecessor::Found(parent) => {
+            // Only the selected cells go back a generation
+            let mut cells = self.engine.cells();
+            cells.retain(|cell, _| !selected.contains_key(cell));
+            cells.extend(parent.into_iter().map(|cell| (cell, 1)));
+            self.engine = self.backend.create(&self.rule, &cells);
+            if self.selection.is_none() {
+              self.tick = self.tick.saturating_sub(1);
+            }
+            self.previous_gens = vec![];
+            self.restart_period_detection();
+            self.history = History::new();
+            self.history.record(self.tick, &self.engine.cells());
+            self.activity = Activity::new();
+            self.activity.record(&self.engine.cells());
+            match self.selection {
+              Some(_) => "Replaced the selection with a predecessor".to_string(),
+              None => "Went back to a predecessor".to_string(),
+            }
+          }
+          Predecessor::Impossible => format!(
+            "Garden of Eden (no predecessor within a margin of {})",
+            PREDECESSOR_MARGIN
+          ),
+          Predecessor::TooBig => "Too big to find a predecessor".to_string(),
+          Predecessor::GaveUp => "Gave up looking for a predecessor".to_string(),
+        });
+        true
+      }
+      Msg::Share => {
+        let snapshot = match self.snapshot() {
+          Some(snapshot) if snapshot.cells.len() <= MAX_SHARED_CELLS => snapshot,
+          _ => {
+            self.identification = Some(format!(
+              "Only boards of up to {} cells can be shared",
+              MAX_SHARED_CELLS
+            ));
+            return true;
+          }
+        };
+        let href = web_sys::window().unwrap().location().href().unwrap();
+        let page = href.split('#').next().unwrap_or_default();
+        self.copy_to_clipboard(&format!("{}#{}", page, snapshot.to_fragment()));
+        self.identification = Some("Copied a link to this board".to_string());
+        true
+      }
+      Msg::OpenLink => {
+        self.interval = None;
+        self.restore_link();
+        true
+      }
+      Msg::CloseCensus => {
+        self.census = None;
+        true
+      }
+      Msg::ToggleCollisionLab => {
+        self.show_collision_lab = !self.show_collision_lab;
+        // The spaceships to choose from are found once, along with the index
+        self.lexicon_index();
         true
       }
       Msg::MoveOffset(offset) => {
@@ -149,20 +768,48 @@ impl Component for Game {
     let resize_handle = EventListener::new(&window, "resize", move |_: &Event| {
       link.send_message(Msg::Resize)
     });
+    let link = ctx.link().clone();
+    let hash_change_handle = EventListener::new(&window, "hashchange", move |_: &Event| {
+      link.send_message(Msg::OpenLink)
+    });
 
-    Self {
-      cells: CellSet::new(),
+    let rule = Rule::default();
+    let backend = Engine::best_for(&rule);
+    let mut game = Self {
+      engine: backend.create(&rule, &CellStates::new()),
+      backend,
+      rule,
       previous_gens: vec![] as Vec<CellSet>,
+      period_detector: PeriodDetector::new(),
+      stabilisation_detector: StabilisationDetector::new(),
+      auto_pause: false,
+      history: History::new(),
+      activity: Activity::new(),
+      render_mode: RenderMode::default(),
+      download_url: None,
+      selection: None,
+      census: None,
+      census_names: HashMap::new(),
+      lexicon_index: None,
+      identification: None,
+      predecessor_search: None,
+      show_collision_lab: false,
       tick: 0,
       interval: None,
       speed: 5,
+      jump: 10,
+      show_copies: false,
+      follow: false,
       adjust_offset: None,
       offset: (0.0, 0.0),
       zoom: 1.0,
       width: 300,
       height: 200,
       _resize_handle: resize_handle,
-    }
+      _hash_change_handle: hash_change_handle,
+    };
+    game.restore_link();
+    game
   }
 
   fn rendered(&mut self, ctx: &Context<Self>, _first_render: bool) {
@@ -173,6 +820,7 @@ impl Component for Game {
 
   fn view(&self, ctx: &Context<Self>) -> yew::virtual_dom::VNode {
     let running = self.interval.is_some();
+    let settings = self.settings(ctx);
 
     let on_change_speed = ctx.link().callback(|event: Event| {
       let input = event
@@ -200,11 +848,65 @@ impl Component for Game {
       })
     };
 
+    let on_change_jump = ctx.link().callback(|event: Event| {
+      let input = event
+        .target()
+        .and_then(|t| t.dyn_into::<HtmlSelectElement>().ok())
+        .unwrap();
+      let jump: u8 = input.value().parse().unwrap();
+      Msg::ChangeJump(jump)
+    });
+
+    let on_change_render_mode = ctx.link().callback(|event: Event| {
+      let input = event
+        .target()
+        .and_then(|t| t.dyn_into::<HtmlSelectElement>().ok())
+        .unwrap();
+      let render_mode: usize = input.value().parse().unwrap();
+      Msg::ChangeRenderMode(RenderMode::ALL[render_mode])
+    });
+
+    let on_change_engine = ctx.link().callback(|event: Event| {
+      let input = event
+        .target()
+        .and_then(|t| t.dyn_into::<HtmlSelectElement>().ok())
+        .unwrap();
+      let engine: usize = input.value().parse().unwrap();
+      Msg::ChangeEngine(Engine::ALL[engine])
+    });
+
+    let (area, level) = self.visible_area(&settings);
+    let (cells, blocks) = if level == 0 {
+      (self.engine.viewport(&area).collect(), vec![])
+    } else {
+      (CellStates::new(), self.engine.blocks(&area, level))
+    };
+
     html! {
       <>
         <Board
-          cells={self.cells.clone()}
+          cells={cells}
           previous_gens={self.previous_gens.clone()}
+          states={self.rule.states}
+          colors={self.rule.colors()}
+          blocks={blocks}
+          topology={self.rule.topology}
+          show_copies={self.show_copies}
+          neighborhood={self.rule.neighborhood()}
+          render_mode={self.render_mode}
+          ages={if self.render_mode == RenderMode::Age {
+            self.activity.ages()
+          } else {
+            Rc::default()
+          }}
+          toggles={if self.render_mode == RenderMode::Activity {
+            self.activity.toggles()
+          } else {
+            Rc::default()
+          }}
+          max_toggles={self.activity.max_toggles()}
+          selection={self.selection}
+          on_select={ctx.link().callback(Msg::Select)}
           offset={self.offset}
           zoom={self.zoom}
           move_offset={ctx.link().callback(move |offset| Msg::MoveOffset(offset))}
@@ -212,6 +914,18 @@ impl Component for Game {
           width={self.width}
           height={self.height}
         />
+        {if self.history.records().is_empty() {
+          html! {}
+        } else {
+          html! {
+            <div class="panel chart-panel">
+              <Chart
+                records={self.history.latest(CHART_GENERATIONS)}
+                on_export={ctx.link().callback(|_| Msg::ExportCsv)}
+              />
+            </div>
+          }
+        }}
         <div class="panel">
           <div class="controls">
             <button disabled={running} onclick={ctx.link().callback(|_| Msg::NextTick)}>{"Tick"}</button>
@@ -222,9 +936,119 @@ impl Component for Game {
                 ctx.link().callback(|_| Msg::Play)
               }
             }>{{if running { "Pause" } else { "Play" }}}</button>
+            <button
+              disabled={running || !self.rule.is_life_like()}
+              onclick={ctx.link().callback(|_| Msg::TakeCensus)}
+            >{"Census"}</button>
+            <button
+              disabled={running || !self.rule.is_conway()}
+              title="Names the selection, or each object of the board"
+              onclick={ctx.link().callback(|_| Msg::Identify)}
+            >{"Identify"}</button>
+            <button
+              disabled={running || !self.rule.is_life_like()}
+              title="Copies the apgcode of the selection, or of the board"
+              onclick={ctx.link().callback(|_| Msg::CopyApgcode)}
+            >{"Apgcode"}</button>
+            <button
+              title="Copies a link to this board"
+              onclick={ctx.link().callback(|_| Msg::Share)}
+            >{"Share"}</button>
+            <button
+              disabled={running || !Predecessor::supports(&self.rule)}
+              title="Goes back a generation, for the selection or the board"
+              onclick={ctx.link().callback(|_| Msg::FindPredecessor)}
+            >{"Back"}</button>
+            <button
+              disabled={!self.rule.is_conway()}
+              onclick={ctx.link().callback(|_| Msg::ToggleCollisionLab)}
+            >{"Collisions"}</button>
             <span class="generation">{format!("Generation #{}", self.tick)}</span>
           </div>
+          {if let Some(objects) = &self.census {
+            html! {
+              <div class="census-panel">
+                <CensusTable objects={objects.clone()} names={self.census_names.clone()} />
+                <button onclick={ctx.link().callback(|_| Msg::CloseCensus)}>{"Close"}</button>
+              </div>
+            }
+          } else {
+            html! {}
+          }}
+          {match &self.lexicon_index {
+            Some(index) if self.show_collision_lab && self.rule.is_conway() => html! {
+              <div class="census-panel">
+                <CollisionLab
+                  rule={self.rule.clone()}
+                  ships={index.spaceships()}
+                  on_apply_pattern={ctx.link().callback(Msg::ApplyPattern)}
+                />
+                <button onclick={ctx.link().callback(|_| Msg::ToggleCollisionLab)}>{"Close"}</button>
+              </div>
+            },
+            _ => html! {},
+          }}
+          <div class="periodicity">{self.period_detector.periodicity().to_string()}</div>
+          {for self.identification.iter().map(|identification| html! {
+            <div class="identification">{identification}</div>
+          })}
+          {for self.predecessor_search.iter().map(|result| html! {
+            <div class="identification">{result}</div>
+          })}
+          {for self.stabilisation_detector.stabilisation().iter().map(|stabilisation| html! {
+            <div class="identification">{stabilisation.to_string()}</div>
+          })}
+          {if self.rule.is_life_like() {
+            html! {
+              <label>
+                <span>{"Pause when stable"}</span>
+                <input
+                  type="checkbox"
+                  checked={self.auto_pause}
+                  onchange={ctx.link().callback(|_| Msg::ToggleAutoPause)}
+                />
+              </label>
+            }
+          } else {
+            html! {}
+          }}
+          {if self.period_detector.periodicity().velocity().is_some() {
+            html! {
+              <label>
+                <span>{"Follow"}</span>
+                <input
+                  type="checkbox"
+                  checked={self.follow}
+                  onchange={ctx.link().callback(|_| Msg::ToggleFollow)}
+                />
+              </label>
+            }
+          } else {
+            html! {}
+          }}
           <PatternSelector on_apply_pattern={ctx.link().callback(|term| Msg::ApplyPattern(term))} />
+          <SoupGenerator on_apply_pattern={ctx.link().callback(Msg::ApplyPattern)} />
+          <PatternFile
+            on_load={ctx.link().callback(Msg::LoadPattern)}
+            on_load_macrocell={ctx.link().callback(Msg::LoadMacrocell)}
+            on_export={ctx.link().callback(Msg::ExportPattern)}
+            has_selection={self.selection.is_some()}
+          />
+          <RulePicker rule={self.rule.clone()} on_change_rule={ctx.link().callback(Msg::ChangeRule)} />
+          {if matches!(self.rule.topology, Topology::Torus { .. }) {
+            html! {
+              <label>
+                <span>{"Copies"}</span>
+                <input
+                  type="checkbox"
+                  checked={self.show_copies}
+                  onchange={ctx.link().callback(|_| Msg::ToggleCopies)}
+                />
+              </label>
+            }
+          } else {
+            html! {}
+          }}
           <label>
             <span>{"Speed"}</span>
             <input
@@ -236,11 +1060,50 @@ impl Component for Game {
           <label>
             <span>{"Zoom"}</span>
             <input
-              type="range" min="0.1" max="5.0" step="0.1"
+              type="range" min="0.02" max="5.0" step="0.02"
               value={self.zoom.to_string()}
               onchange={on_change_zoom}
             />
           </label>
+          <label>
+            <span>{"Colors"}</span>
+            <select onchange={on_change_render_mode}>
+              {for RenderMode::ALL.iter().enumerate().map(|(i, render_mode)| html! {
+                <option
+                  value={i.to_string()}
+                  selected={self.render_mode == *render_mode}
+                >{render_mode.name()}</option>
+              })}
+            </select>
+          </label>
+          <label>
+            <span>{"Engine"}</span>
+            <select onchange={on_change_engine}>
+              {for Engine::ALL.iter().enumerate().map(|(i, engine)| html! {
+                <option
+                  value={i.to_string()}
+                  selected={self.backend == *engine}
+                  disabled={!engine.supports(&self.rule)}
+                >{engine.name()}</option>
+              })}
+            </select>
+          </label>
+          <label>
+            <span>{"Jump"}</span>
+            <select onchange={on_change_jump}>
+              {for (0..=30).map(|jump| html! {
+                <option
+                  value={jump.to_string()}
+                  selected={self.jump == jump}
+                >{format!("2^{} generations", jump)}</option>
+              })}
+            </select>
+            <button
+              class="jump"
+              disabled={running || !self.rule.is_life_like()}
+              onclick={ctx.link().callback(|_| Msg::Jump)}
+            >{"Go"}</button>
+          </label>
           <div class="about">
             {"Made by "}
             <a href="https://twitter.com/scastiel" target="_blank" rel="noopener noreferrer">{"Sébastien Castiel"}</a>
@@ -1,3 +1,9 @@
 pub mod board;
+pub mod census_table;
+pub mod chart;
+pub mod collision_lab;
 pub mod game;
+pub mod pattern_file;
 pub mod pattern_selector;
+pub mod rule_picker;
+pub mod soup_generator;
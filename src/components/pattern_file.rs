@@ -0,0 +1,152 @@
+use crate::life::{Format, Macrocell, Pattern};
+use gloo::file::callbacks::{read_as_text, FileReader};
+use gloo::file::File;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlInputElement, HtmlSelectElement, HtmlTextAreaElement};
+use yew::prelude::*;
+
+/// Imports patterns from files or pasted text, and exports the board.
+pub struct PatternFile {
+  text: String,
+  /// The format to export to.
+  format: Format,
+  error: Option<String>,
+  reader: Option<FileReader>,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+  pub on_load: Callback<Pattern>,
+  /// Called instead of `on_load` for macrocell files, which are kept as
+  /// quadtrees.
+  pub on_load_macrocell: Callback<Macrocell>,
+  /// Called to export the selection if there is one, the board otherwise.
+  pub on_export: Callback<Format>,
+  #[prop_or_default]
+  pub has_selection: bool,
+}
+
+pub enum Msg {
+  Input(String),
+  ChangeFormat(Format),
+  Load,
+  LoadFile(File),
+  FileLoaded(Result<String, String>),
+}
+
+impl PatternFile {
+  fn load(&mut self, ctx: &Context<Self>, text: &str) {
+    let loaded = if Format::detect(text) == Format::Macrocell {
+      Macrocell::parse(text).map(|macrocell| ctx.props().on_load_macrocell.emit(macrocell))
+    } else {
+      Pattern::parse(text).map(|pattern| ctx.props().on_load.emit(pattern))
+    };
+    self.error = loaded.err().map(|error| error.to_string());
+  }
+}
+
+impl Component for PatternFile {
+  type Message = Msg;
+  type Properties = Props;
+
+  fn create(_ctx: &Context<Self>) -> Self {
+    Self {
+      text: String::new(),
+      format: Format::Rle,
+      error: None,
+      reader: None,
+    }
+  }
+
+  fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+    match msg {
+      Msg::Input(text) => {
+        self.text = text;
+        self.error = None;
+      }
+      Msg::ChangeFormat(format) => self.format = format,
+      Msg::Load => {
+        let text = self.text.clone();
+        self.load(ctx, &text);
+      }
+      Msg::LoadFile(file) => {
+        let link = ctx.link().clone();
+        self.reader = Some(read_as_text(&file, move |text| {
+          link.send_message(Msg::FileLoaded(text.map_err(|error| error.to_string())))
+        }));
+        return false;
+      }
+      Msg::FileLoaded(text) => {
+        self.reader = None;
+        match text {
+          Ok(text) => self.load(ctx, &text),
+          Err(error) => self.error = Some(error),
+        }
+      }
+    }
+    true
+  }
+
+  fn view(&self, ctx: &Context<Self>) -> yew::virtual_dom::VNode {
+    let on_input = ctx.link().callback(|event: InputEvent| {
+      let input = event
+        .target()
+        .and_then(|t| t.dyn_into::<HtmlTextAreaElement>().ok())
+        .unwrap();
+      Msg::Input(input.value())
+    });
+
+    let on_change_format = ctx.link().callback(|event: Event| {
+      let input = event
+        .target()
+        .and_then(|t| t.dyn_into::<HtmlSelectElement>().ok())
+        .unwrap();
+      let format: usize = input.value().parse().unwrap();
+      Msg::ChangeFormat(Format::ALL[format])
+    });
+
+    let format = self.format;
+    let on_change_file = ctx.link().batch_callback(|event: Event| {
+      let input = event
+        .target()
+        .and_then(|t| t.dyn_into::<HtmlInputElement>().ok())
+        .unwrap();
+      let file = input.files().and_then(|files| files.get(0));
+      file.map(|file| Msg::LoadFile(File::from(file)))
+    });
+
+    html! {
+      <div class="pattern-file">
+        <textarea
+          placeholder="Paste a pattern (RLE, plaintext, Life 1.05 or 1.06, macrocell) or an apgcode like xq4_153…"
+          value={self.text.clone()}
+          oninput={on_input}
+        />
+        <div class="pattern-file-buttons">
+          <button
+            disabled={self.text.trim().is_empty()}
+            onclick={ctx.link().callback(|_| Msg::Load)}
+          >{"Load"}</button>
+          <button onclick={ctx.props().on_export.reform(move |_| format)}>
+            {if ctx.props().has_selection { "Export selection" } else { "Export board" }}
+          </button>
+          <select onchange={on_change_format}>
+            {for Format::ALL.iter().enumerate().map(|(i, format)| html! {
+              <option
+                value={i.to_string()}
+                selected={self.format == *format}
+              >{format.name()}</option>
+            })}
+          </select>
+        </div>
+        <label class="pattern-upload">
+          <span>{"Pattern file"}</span>
+          <input type="file" accept=".rle,.cells,.lif,.life,.mc,.txt" onchange={on_change_file}/>
+        </label>
+        {for self.error.iter().map(|error| html! {
+          <div class="pattern-error">{error}</div>
+        })}
+      </div>
+    }
+  }
+}
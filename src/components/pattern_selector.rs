@@ -1,3 +1,4 @@
+use crate::life::CellSet;
 use lexicon::*;
 use wasm_bindgen::JsCast;
 use web_sys::HtmlSelectElement;
@@ -88,3 +89,24 @@ fn format_term_option(term: &Term) -> String {
     }
   )
 }
+
+/// A pattern made of some cells, moved to start at the top left corner.
+pub fn term_from_cells(name: String, description: String, tag: &str, cells: &CellSet) -> Term {
+  let left = cells.iter().map(|cell| cell.x).min().unwrap_or(0);
+  let top = cells.iter().map(|cell| cell.y).min().unwrap_or(0);
+  let cells: Vec<Cell> = cells
+    .iter()
+    .map(|cell| Cell {
+      x: cell.x - left,
+      y: cell.y - top,
+    })
+    .collect();
+  Term {
+    name,
+    description,
+    tags: vec![tag.to_string()],
+    width: cells.iter().map(|cell| cell.x + 1).max().unwrap_or(0) as usize,
+    height: cells.iter().map(|cell| cell.y + 1).max().unwrap_or(0) as usize,
+    cells,
+  }
+}
@@ -0,0 +1,189 @@
+use crate::life::{Rule, RuleTable};
+use gloo::file::callbacks::{read_as_text, FileReader};
+use gloo::file::File;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+const PRESETS: [(&str, &str); 16] = [
+  ("Conway’s Life", "B3/S23"),
+  ("HighLife", "B36/S23"),
+  ("Day & Night", "B3678/S34678"),
+  ("Seeds", "B2/S"),
+  ("Life without Death", "B3/S012345678"),
+  ("2x2", "B36/S125"),
+  ("Replicator", "B1357/S1357"),
+  ("Morley", "B368/S245"),
+  ("tlife", "B3/S2-i34q"),
+  ("Hexagonal Life", "B2/S34H"),
+  ("von Neumann Life", "B3/S23V"),
+  ("Brian’s Brain", "B2/S/C3"),
+  ("Star Wars", "B2/S345/C4"),
+  ("Bosco’s Rule", "R5,C0,M1,S34..58,B34..45,NM"),
+  ("Majority", "R4,C0,M1,S41..81,B41..81,NM"),
+  ("WireWorld", "WireWorld"),
+];
+
+pub struct RulePicker {
+  /// The rule last given by the parent, to notice when it changes.
+  rule: Rule,
+  value: String,
+  error: Option<String>,
+  reader: Option<FileReader>,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+  pub rule: Rule,
+  pub on_change_rule: Callback<Rule>,
+}
+
+pub enum Msg {
+  Input(String),
+  PresetSelected(usize),
+  Apply,
+  LoadFile(File),
+  FileLoaded(Result<String, String>),
+}
+
+impl Component for RulePicker {
+  type Message = Msg;
+  type Properties = Props;
+
+  fn create(ctx: &Context<Self>) -> Self {
+    Self {
+      rule: ctx.props().rule.clone(),
+      value: ctx.props().rule.to_string(),
+      error: None,
+      reader: None,
+    }
+  }
+
+  fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+    match msg {
+      Msg::Input(value) => {
+        self.value = value;
+        self.error = None;
+        true
+      }
+      Msg::PresetSelected(preset) => {
+        self.value = PRESETS[preset].1.to_string();
+        ctx.link().send_message(Msg::Apply);
+        true
+      }
+      // Rules loaded from a file can't be parsed back from their name
+      Msg::Apply if self.value == ctx.props().rule.to_string() => {
+        self.error = None;
+        true
+      }
+      Msg::Apply => {
+        match self.value.parse::<Rule>() {
+          Ok(rule) => {
+            self.value = rule.to_string();
+            self.error = None;
+            ctx.props().on_change_rule.emit(rule);
+          }
+          Err(error) => self.error = Some(error.to_string()),
+        }
+        true
+      }
+      Msg::LoadFile(file) => {
+        let link = ctx.link().clone();
+        self.reader = Some(read_as_text(&file, move |text| {
+          link.send_message(Msg::FileLoaded(text.map_err(|error| error.to_string())))
+        }));
+        false
+      }
+      Msg::FileLoaded(text) => {
+        self.reader = None;
+        match text.and_then(|text| RuleTable::parse(&text).map_err(|error| error.to_string())) {
+          Ok(table) => {
+            let rule = Rule::from_table(table, ctx.props().rule.topology);
+            self.value = rule.to_string();
+            self.error = None;
+            ctx.props().on_change_rule.emit(rule);
+          }
+          Err(error) => self.error = Some(error),
+        }
+        true
+      }
+    }
+  }
+
+  /// Shows rules changed from elsewhere, like the ones of loaded patterns.
+  fn changed(&mut self, ctx: &Context<Self>) -> bool {
+    if ctx.props().rule != self.rule {
+      self.rule = ctx.props().rule.clone();
+      self.value = self.rule.to_string();
+      self.error = None;
+    }
+    true
+  }
+
+  fn view(&self, ctx: &Context<Self>) -> yew::virtual_dom::VNode {
+    let on_input = ctx.link().callback(|event: InputEvent| {
+      let input = event
+        .target()
+        .and_then(|t| t.dyn_into::<HtmlInputElement>().ok())
+        .unwrap();
+      Msg::Input(input.value())
+    });
+
+    let on_change_preset = ctx.link().callback(|event: Event| {
+      let input = event
+        .target()
+        .and_then(|t| t.dyn_into::<HtmlSelectElement>().ok())
+        .unwrap();
+      let preset: usize = input.value().parse().unwrap();
+      Msg::PresetSelected(preset)
+    });
+
+    let on_change_file = ctx.link().batch_callback(|event: Event| {
+      let input = event
+        .target()
+        .and_then(|t| t.dyn_into::<HtmlInputElement>().ok())
+        .unwrap();
+      let file = input.files().and_then(|files| files.get(0));
+      file.map(|file| Msg::LoadFile(File::from(file)))
+    });
+
+    let current_rule = ctx.props().rule.to_string();
+
+    html! {
+      <div class="rule-picker">
+        <label>
+          <span>{"Rule"}</span>
+          <input
+            type="text"
+            class={classes!(self.error.is_some().then(|| "invalid"))}
+            value={self.value.clone()}
+            oninput={on_input}
+            onkeypress={ctx.link().batch_callback(|event: KeyboardEvent| {
+              (event.key() == "Enter").then(|| Msg::Apply)
+            })}
+          />
+          <button onclick={ctx.link().callback(|_| Msg::Apply)}>{"Apply"}</button>
+        </label>
+        <select onchange={on_change_preset}>
+          <option
+            disabled={true}
+            selected={PRESETS.iter().all(|(_, rule)| *rule != current_rule)}
+          >{"Presets…"}</option>
+          {for PRESETS.iter().enumerate().map(|(i, (name, rule))| html! {
+            <option
+              value={i.to_string()}
+              selected={*rule == current_rule}
+            >{format!("{} ({})", name, rule)}</option>
+          })}
+        </select>
+        <label class="rule-file">
+          <span>{"Rule file"}</span>
+          <input type="file" accept=".rule" onchange={on_change_file}/>
+        </label>
+        {for self.error.iter().map(|error| html! {
+          <div class="rule-error">{error}</div>
+        })}
+      </div>
+    }
+  }
+}
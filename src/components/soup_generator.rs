@@ -0,0 +1,160 @@
+use crate::components::pattern_selector::term_from_cells;
+use crate::life::{soup, SYMMETRIES};
+use lexicon::Term;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+/// The largest soup side, soups growing with its square.
+const MAX_SIZE: u32 = 256;
+
+pub struct SoupGenerator {
+  seed: String,
+  size: u32,
+  density: u8,
+  symmetry: usize,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+  pub on_apply_pattern: Callback<Term>,
+}
+
+pub enum Msg {
+  ChangeSeed(String),
+  ChangeSize(u32),
+  ChangeDensity(u8),
+  ChangeSymmetry(usize),
+  NextSeed,
+  Generate,
+}
+
+/// The seed with its trailing number incremented, to go through a series
+/// of soups. A number too large to be incremented starts a new series after
+/// it.
+fn next_seed(seed: &str) -> String {
+  let prefix = seed.trim_end_matches(|c: char| c.is_ascii_digit());
+  let number = &seed[prefix.len()..];
+  match number.parse::<u64>().ok().and_then(|n| n.checked_add(1)) {
+    Some(next) => format!("{}{}", prefix, next),
+    None if number.is_empty() => format!("{}1", seed),
+    None => format!("{}-1", seed),
+  }
+}
+
+impl SoupGenerator {
+  fn term(&self) -> Term {
+    let symmetry = &SYMMETRIES[self.symmetry];
+    let cells = soup(&self.seed, self.size, self.density as f64 / 100.0, symmetry);
+    term_from_cells(
+      format!("Soup {}", self.seed),
+      format!(
+        "Seed {}, {}×{} at {}% density, {} symmetry",
+        self.seed, self.size, self.size, self.density, symmetry.name
+      ),
+      "soup",
+      &cells,
+    )
+  }
+}
+
+impl Component for SoupGenerator {
+  type Message = Msg;
+  type Properties = Props;
+
+  fn create(_ctx: &Context<Self>) -> Self {
+    Self {
+      seed: "soup_1".to_string(),
+      size: 16,
+      density: 50,
+      symmetry: 0,
+    }
+  }
+
+  fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+    match msg {
+      Msg::ChangeSeed(seed) => self.seed = seed,
+      Msg::ChangeSize(size) => self.size = size.clamp(1, MAX_SIZE),
+      Msg::ChangeDensity(density) => self.density = density.min(100),
+      Msg::ChangeSymmetry(symmetry) => self.symmetry = symmetry,
+      Msg::NextSeed => {
+        self.seed = next_seed(&self.seed);
+        ctx.link().send_message(Msg::Generate);
+      }
+      Msg::Generate => ctx.props().on_apply_pattern.emit(self.term()),
+    }
+    true
+  }
+
+  fn view(&self, ctx: &Context<Self>) -> yew::virtual_dom::VNode {
+    let input_value = |event: Event| {
+      event
+        .target()
+        .and_then(|t| t.dyn_into::<HtmlInputElement>().ok())
+        .unwrap()
+        .value()
+    };
+
+    let on_input_seed = ctx.link().callback(|event: InputEvent| {
+      let input = event
+        .target()
+        .and_then(|t| t.dyn_into::<HtmlInputElement>().ok())
+        .unwrap();
+      Msg::ChangeSeed(input.value())
+    });
+
+    let on_change_size = ctx
+      .link()
+      .batch_callback(move |event: Event| input_value(event).parse().ok().map(Msg::ChangeSize));
+
+    let on_change_density = ctx
+      .link()
+      .batch_callback(move |event: Event| input_value(event).parse().ok().map(Msg::ChangeDensity));
+
+    let on_change_symmetry = ctx.link().callback(|event: Event| {
+      let input = event
+        .target()
+        .and_then(|t| t.dyn_into::<HtmlSelectElement>().ok())
+        .unwrap();
+      let symmetry: usize = input.value().parse().unwrap();
+      Msg::ChangeSymmetry(symmetry)
+    });
+
+    html! {
+      <div class="soup-generator">
+        <label>
+          <span>{"Soup seed"}</span>
+          <input type="text" value={self.seed.clone()} oninput={on_input_seed}/>
+        </label>
+        <label>
+          <span>{"Soup size"}</span>
+          <input
+            type="number" min="1" max={MAX_SIZE.to_string()}
+            value={self.size.to_string()}
+            onchange={on_change_size}
+          />
+          <select onchange={on_change_symmetry}>
+            {for SYMMETRIES.iter().enumerate().map(|(i, symmetry)| html! {
+              <option
+                value={i.to_string()}
+                selected={self.symmetry == i}
+              >{symmetry.name}</option>
+            })}
+          </select>
+        </label>
+        <label>
+          <span>{format!("Density {}%", self.density)}</span>
+          <input
+            type="range" min="0" max="100"
+            value={self.density.to_string()}
+            onchange={on_change_density}
+          />
+        </label>
+        <div class="soup-buttons">
+          <button onclick={ctx.link().callback(|_| Msg::Generate)}>{"Generate"}</button>
+          <button onclick={ctx.link().callback(|_| Msg::NextSeed)}>{"Next seed"}</button>
+        </div>
+      </div>
+    }
+  }
+}
@@ -0,0 +1,94 @@
+use crate::life::{CellSet, CellStates};
+use lexicon::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// How long cells have been alive and how often they changed, generation
+/// after generation. A cell is alive when in state 1. The counts are shared
+/// with the board rather than copied each time it is drawn.
+#[derive(Default)]
+pub struct Activity {
+  /// The number of generations each live cell has been alive in a row.
+  ages: Rc<HashMap<Cell, u64>>,
+  /// The number of births and deaths of every cell that ever changed.
+  toggles: Rc<HashMap<Cell, u64>>,
+  max_toggles: u64,
+  started: bool,
+}
+
+impl Activity {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn ages(&self) -> Rc<HashMap<Cell, u64>> {
+    Rc::clone(&self.ages)
+  }
+
+  pub fn toggles(&self) -> Rc<HashMap<Cell, u64>> {
+    Rc::clone(&self.toggles)
+  }
+
+  pub fn max_toggles(&self) -> u64 {
+    self.max_toggles
+  }
+
+  /// Adds the next generation. The first one only sets the ages, which start
+  /// at 1 for the cells alive then.
+  pub fn record(&mut self, cells: &CellStates) {
+    let alive: CellSet = cells
+      .iter()
+      .filter(|(_, &state)| state == 1)
+      .map(|(&cell, _)| cell)
+      .collect();
+    let ages = alive
+      .iter()
+      .map(|cell| (*cell, self.ages.get(cell).map_or(1, |age| age + 1)))
+      .collect();
+    if self.started {
+      let deaths = self.ages.keys().filter(|cell| !alive.contains(cell));
+      let births = alive.iter().filter(|cell| !self.ages.contains_key(cell));
+      let changed: Vec<Cell> = deaths.chain(births).copied().collect();
+      // The counts are only copied if the board still holds them
+      if !changed.is_empty() {
+        let all_toggles = Rc::make_mut(&mut self.toggles);
+        for cell in changed {
+          let toggles = all_toggles.entry(cell).or_insert(0);
+          *toggles += 1;
+          self.max_toggles = self.max_toggles.max(*toggles);
+        }
+      }
+    }
+    self.ages = Rc::new(ages);
+    self.started = true;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::life::{from_alive_cells, step, Rule};
+
+  #[test]
+  fn finds_the_rotor_of_a_blinker() {
+    let rule = Rule::conway();
+    let blinker: CellSet = [(0, 1), (1, 1), (2, 1)]
+      .iter()
+      .map(|&(x, y)| Cell { x, y })
+      .collect();
+    let mut cells = from_alive_cells(&blinker);
+    let mut activity = Activity::new();
+    activity.record(&cells);
+    for _ in 0..3 {
+      cells = step(&cells, &rule);
+      activity.record(&cells);
+    }
+    assert_eq!(activity.ages()[&Cell { x: 1, y: 1 }], 4);
+    assert_eq!(activity.ages()[&Cell { x: 1, y: 0 }], 1);
+    assert_eq!(activity.toggles().get(&Cell { x: 1, y: 1 }), None);
+    assert_eq!(activity.toggles()[&Cell { x: 0, y: 1 }], 3);
+    assert_eq!(activity.toggles()[&Cell { x: 1, y: 2 }], 3);
+    assert_eq!(activity.toggles().len(), 4);
+    assert_eq!(activity.max_toggles(), 3);
+  }
+}
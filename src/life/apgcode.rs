@@ -0,0 +1,255 @@
+use crate::life::engine::bounding_box;
+use crate::life::{from_alive_cells, CellSet, ParsePatternError, Pattern};
+use lexicon::Cell;
+
+const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// Names of the objects most often found in soups.
+const NAMES: [(&str, &str); 16] = [
+  ("xs4_33", "block"),
+  ("xs4_252", "tub"),
+  ("xs5_253", "boat"),
+  ("xs6_356", "ship"),
+  ("xs6_696", "beehive"),
+  ("xs6_25a4", "barge"),
+  ("xs7_2596", "loaf"),
+  ("xs7_25ac", "long boat"),
+  ("xs8_6996", "pond"),
+  ("xp2_7", "blinker"),
+  ("xp2_7e", "toad"),
+  ("xp2_318c", "beacon"),
+  ("xq4_153", "glider"),
+  ("xq4_6frc", "lightweight spaceship"),
+  ("xq4_27dee6", "middleweight spaceship"),
+  ("xq4_27deee6", "heavyweight spaceship"),
+];
+
+pub fn common_name(apgcode: &str) -> Option<&'static str> {
+  NAMES
+    .iter()
+    .find(|(code, _)| *code == apgcode)
+    .map(|&(_, name)| name)
+}
+
+/// The 8 rotations and reflections of some cells.
+pub fn orientations(cells: &CellSet) -> [CellSet; 8] {
+  let transform = |(a, b, c, d): (i32, i32, i32, i32)| {
+    cells
+      .iter()
+      .map(|cell| Cell {
+        x: a * cell.x + b * cell.y,
+        y: c * cell.x + d * cell.y,
+      })
+      .collect()
+  };
+  [
+    transform((1, 0, 0, 1)),
+    transform((0, -1, 1, 0)),
+    transform((-1, 0, 0, -1)),
+    transform((0, 1, -1, 0)),
+    transform((-1, 0, 0, 1)),
+    transform((1, 0, 0, -1)),
+    transform((0, 1, 1, 0)),
+    transform((0, -1, -1, 0)),
+  ]
+}
+
+/// The extended Wechsler format of some cells: strips of 5 rows, each column
+/// of a strip being a base-32 digit, with runs of empty columns shortened.
+pub fn wechsler(cells: &CellSet) -> String {
+  let (top_left, bottom_right) = match bounding_box(cells.iter().copied()) {
+    Some(bounds) => bounds,
+    None => return String::new(),
+  };
+  let mut code = String::new();
+  for strip in 0..=(bottom_right.y - top_left.y) / 5 {
+    if strip > 0 {
+      code.push('z');
+    }
+    let mut zeros = 0;
+    for x in top_left.x..=bottom_right.x {
+      let column = (0..5).fold(0, |column, row| {
+        let cell = Cell {
+          x,
+          y: top_left.y + strip * 5 + row,
+        };
+        column | (cells.contains(&cell) as usize) << row
+      });
+      if column == 0 {
+        zeros += 1;
+        continue;
+      }
+      while zeros >= 40 {
+        code.push_str("yz");
+        zeros -= 39;
+      }
+      match zeros {
+        0 => {}
+        1 => code.push('0'),
+        2 => code.push('w'),
+        3 => code.push('x'),
+        _ => {
+          code.push('y');
+          code.push(DIGITS[zeros - 4] as char);
+        }
+      }
+      zeros = 0;
+      code.push(DIGITS[column] as char);
+    }
+  }
+  code
+}
+
+/// The cells of an extended Wechsler code, from the top left corner.
+pub fn from_wechsler(code: &str) -> Result<CellSet, ParsePatternError> {
+  let digit = |c: char| DIGITS.iter().position(|&digit| digit as char == c);
+  let mut cells = CellSet::new();
+  let (mut x, mut y) = (0, 0);
+  let mut chars = code.chars();
+  while let Some(c) = chars.next() {
+    match c {
+      'w' => x += 2,
+      'x' => x += 3,
+      'y' => {
+        let zeros = chars.next().and_then(digit).ok_or_else(|| {
+          ParsePatternError(format!("Invalid run of empty columns in '{}'", code))
+        })?;
+        x += 4 + zeros as i32;
+      }
+      'z' => {
+        x = 0;
+        y += 5;
+      }
+      _ => {
+        let column = digit(c)
+          .filter(|&column| column < 32)
+          .ok_or_else(|| ParsePatternError(format!("Unexpected '{}' in '{}'", c, code)))?;
+        for row in 0..5 {
+          if column & 1 << row != 0 {
+            cells.insert(Cell { x, y: y + row });
+          }
+        }
+        x += 1;
+      }
+    }
+  }
+  Ok(cells)
+}
+
+/// The cells of the apgcode of a still life (`xs`), an oscillator (`xp`) or
+/// a spaceship (`xq`), such as `xq4_153` for the glider.
+pub fn apgcode_cells(apgcode: &str) -> Result<CellSet, ParsePatternError> {
+  let invalid = || ParsePatternError(format!("Invalid apgcode '{}'", apgcode));
+  let (prefix, code) = apgcode.split_once('_').ok_or_else(invalid)?;
+  let (kind, number) = ["xs", "xp", "xq"]
+    .into_iter()
+    .find_map(|kind| Some((kind, prefix.strip_prefix(kind)?)))
+    .ok_or_else(invalid)?;
+  let number: usize = number.parse().map_err(|_| invalid())?;
+  if number == 0 {
+    return Err(invalid());
+  }
+  let cells = from_wechsler(code)?;
+  // Still lives give their population
+  if cells.is_empty() || kind == "xs" && cells.len() != number {
+    return Err(invalid());
+  }
+  Ok(cells)
+}
+
+/// The representation Catagolue prefers among all phases and orientations:
+/// the shortest one, then the first in alphabetical order.
+pub fn canonical_wechsler(phases: &[CellSet]) -> String {
+  phases
+    .iter()
+    .flat_map(orientations)
+    .map(|cells| wechsler(&cells))
+    .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+    .unwrap_or_default()
+}
+
+impl Pattern {
+  /// The object of an apgcode, named after it unless it has a common name.
+  pub fn from_apgcode(apgcode: &str) -> Result<Self, ParsePatternError> {
+    let cells = apgcode_cells(apgcode)?;
+    let mut pattern = Pattern::new(from_alive_cells(&cells));
+    pattern.name = Some(common_name(apgcode).unwrap_or(apgcode).to_string());
+    Ok(pattern)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::life::{tick, Rule};
+
+  fn cells(cells: &[(i32, i32)]) -> CellSet {
+    cells.iter().map(|&(x, y)| Cell { x, y }).collect()
+  }
+
+  fn phases(cells: CellSet, period: usize) -> Vec<CellSet> {
+    std::iter::successors(Some(cells), |cells| Some(tick(cells, &Rule::conway())))
+      .take(period)
+      .collect()
+  }
+
+  #[test]
+  fn encodes_common_objects() {
+    let block = cells(&[(0, 0), (1, 0), (0, 1), (1, 1)]);
+    assert_eq!(canonical_wechsler(&[block]), "33");
+    let beehive = cells(&[(1, 0), (2, 0), (0, 1), (3, 1), (1, 2), (2, 2)]);
+    assert_eq!(canonical_wechsler(&[beehive]), "696");
+    let blinker = cells(&[(0, 0), (1, 0), (2, 0)]);
+    assert_eq!(canonical_wechsler(&phases(blinker, 2)), "7");
+    let glider = cells(&[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+    assert_eq!(canonical_wechsler(&phases(glider, 4)), "153");
+    let lwss = cells(&[
+      (1, 0),
+      (4, 0),
+      (0, 1),
+      (0, 2),
+      (4, 2),
+      (0, 3),
+      (1, 3),
+      (2, 3),
+      (3, 3),
+    ]);
+    assert_eq!(canonical_wechsler(&phases(lwss, 4)), "6frc");
+  }
+
+  #[test]
+  fn shortens_empty_columns_and_splits_strips() {
+    assert_eq!(wechsler(&cells(&[(0, 0), (5, 0)])), "1y01");
+    assert_eq!(wechsler(&cells(&[(0, 0), (3, 0)])), "1w1");
+    assert_eq!(wechsler(&cells(&[(0, 0), (0, 5)])), "1z1");
+  }
+
+  #[test]
+  fn decodes_apgcodes() {
+    let glider = cells(&[(0, 0), (1, 0), (2, 0), (2, 1), (1, 2)]);
+    assert_eq!(apgcode_cells("xq4_153"), Ok(glider));
+    for code in [
+      "xs4_33",
+      "xp2_7e",
+      "xq4_27deee6",
+      "xp15_4r4z4r4",
+      "xs4_1y01z1y01",
+      "xs4_1w1z1w1",
+    ] {
+      let cells = apgcode_cells(code).unwrap();
+      assert_eq!(
+        wechsler(&cells),
+        code.split_once('_').unwrap().1,
+        "{}",
+        code
+      );
+    }
+    let long_gap = cells(&[(0, 0), (45, 0)]);
+    assert_eq!(from_wechsler(&wechsler(&long_gap)), Ok(long_gap));
+    for invalid in [
+      "xq4", "xs5_33", "xr4_153", "xq_153", "xp2_7W", "xp2_y", "xs0_", "aé_1", "xé_1",
+    ] {
+      assert!(apgcode_cells(invalid).is_err(), "{}", invalid);
+    }
+  }
+}
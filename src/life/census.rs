@@ -0,0 +1,259 @@
+use crate::life::apgcode::canonical_wechsler;
+use crate::life::{from_alive_cells, tick, CellSet, PeriodDetector, Periodicity, Rule};
+use lexicon::Cell;
+use std::collections::HashMap;
+
+/// How many generations an object is run for before calling it unstable.
+const MAX_GENERATIONS: u64 = 1000;
+
+/// Cells closer than this (in both directions) can affect a common cell, so
+/// they belong to the same object.
+const INTERACTION_DISTANCE: i32 = 2;
+
+/// An independent part of the universe.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Object {
+  pub cells: CellSet,
+  /// How it evolves, or `NotYetPeriodic` if it doesn’t settle down.
+  pub periodicity: Periodicity,
+  /// Its Catagolue code, unless it is unstable.
+  pub apgcode: Option<String>,
+}
+
+impl Object {
+  /// Runs the cells on their own to find how they evolve, along with all
+  /// their phases.
+  pub(crate) fn analyse(cells: CellSet, rule: &Rule, max_generations: u64) -> (Self, Vec<CellSet>) {
+    let mut detector = PeriodDetector::new();
+    let mut generations = vec![];
+    let mut current = cells.clone();
+    for generation in 0..max_generations {
+      detector.record(generation, &from_alive_cells(&current));
+      if detector.periodicity() != Periodicity::NotYetPeriodic {
+        break;
+      }
+      let next = tick(&current, rule);
+      generations.push(current);
+      current = next;
+    }
+    let periodicity = detector.periodicity();
+    let (period, prefix) = match periodicity {
+      Periodicity::StillLife => (1, format!("xs{}", cells.len())),
+      Periodicity::Oscillator { period } => (period, format!("xp{}", period)),
+      Periodicity::Spaceship { period, .. } => (period, format!("xq{}", period)),
+      _ => (0, String::new()),
+    };
+    // Patterns only repeating after a while haven’t settled down yet
+    let (periodicity, phases) = if period > 0 && generations.len() as u64 == period {
+      (periodicity, generations)
+    } else {
+      (Periodicity::NotYetPeriodic, vec![cells.clone()])
+    };
+    let apgcode = (periodicity != Periodicity::NotYetPeriodic)
+      .then(|| format!("{}_{}", prefix, canonical_wechsler(&phases)));
+    let object = Self {
+      cells,
+      periodicity,
+      apgcode,
+    };
+    (object, phases)
+  }
+}
+
+/// The apgcode of some cells taken as a single object, if it is stable.
+pub fn apgcode_of(cells: &CellSet, rule: &Rule) -> Option<String> {
+  Object::analyse(cells.clone(), rule, MAX_GENERATIONS)
+    .0
+    .apgcode
+}
+
+/// The objects of a pattern, as found by apgsearch: cells are grouped when
+/// they are close enough to interact, in any phase of their evolution.
+pub fn census(cells: &CellSet, rule: &Rule) -> Vec<Object> {
+  census_within(cells, rule, MAX_GENERATIONS)
+}
+
+/// A census running each group of cells for at most some generations.
+pub(crate) fn census_within(cells: &CellSet, rule: &Rule, max_generations: u64) -> Vec<Object> {
+  let mut groups = split(cells.iter().map(|&cell| (cell, cell)));
+  loop {
+    let (objects, envelopes): (Vec<Object>, Vec<CellSet>) = groups
+      .into_iter()
+      .map(|group| {
+        let (object, phases) = Object::analyse(group, rule, max_generations);
+        let envelope = phases.into_iter().flatten().collect();
+        (object, envelope)
+      })
+      .unzip();
+    // Each cell of an envelope stands for a whole object
+    let representatives = objects
+      .iter()
+      .zip(envelopes.iter())
+      .flat_map(|(object, envelope)| {
+        let representative = *object.cells.iter().next().unwrap();
+        envelope.iter().map(move |&cell| (cell, representative))
+      });
+    let merged = split(representatives);
+    if merged.len() == objects.len() {
+      return objects;
+    }
+    groups = merged
+      .into_iter()
+      .map(|representatives| {
+        objects
+          .iter()
+          .filter(|object| {
+            representatives
+              .iter()
+              .any(|cell| object.cells.contains(cell))
+          })
+          .flat_map(|object| object.cells.iter().copied())
+          .collect()
+      })
+      .collect();
+  }
+}
+
+/// Groups the values of cells that are close to each other.
+fn split(cells: impl Iterator<Item = (Cell, Cell)>) -> Vec<CellSet> {
+  let mut values: HashMap<Cell, Vec<Cell>> = HashMap::new();
+  for (cell, value) in cells {
+    values.entry(cell).or_default().push(value);
+  }
+  let mut groups = vec![];
+  let mut visited = CellSet::new();
+  for &start in values.keys() {
+    if !visited.insert(start) {
+      continue;
+    }
+    let mut group = CellSet::new();
+    let mut stack = vec![start];
+    while let Some(cell) = stack.pop() {
+      group.extend(values[&cell].iter().copied());
+      for dy in -INTERACTION_DISTANCE..=INTERACTION_DISTANCE {
+        for dx in -INTERACTION_DISTANCE..=INTERACTION_DISTANCE {
+          let neighbor = Cell {
+            x: cell.x + dx,
+            y: cell.y + dy,
+          };
+          if values.contains_key(&neighbor) && visited.insert(neighbor) {
+            stack.push(neighbor);
+          }
+        }
+      }
+    }
+    groups.push(group);
+  }
+  groups
+}
+
+/// How many objects of each kind there are, the most common first.
+pub fn tally(objects: &[Object]) -> Vec<(Option<String>, usize)> {
+  let mut counts: HashMap<Option<String>, usize> = HashMap::new();
+  for object in objects {
+    *counts.entry(object.apgcode.clone()).or_default() += 1;
+  }
+  let mut counts: Vec<_> = counts.into_iter().collect();
+  counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+  counts
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn cells(cells: &[(i32, i32)], (dx, dy): (i32, i32)) -> Vec<Cell> {
+    cells
+      .iter()
+      .map(|&(x, y)| Cell {
+        x: x + dx,
+        y: y + dy,
+      })
+      .collect()
+  }
+
+  #[test]
+  fn splits_and_identifies_objects() {
+    let block = [(0, 0), (1, 0), (0, 1), (1, 1)];
+    let blinker = [(0, 0), (1, 0), (2, 0)];
+    let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+    let pattern: CellSet = [
+      cells(&block, (0, 0)),
+      cells(&block, (10, 0)),
+      cells(&blinker, (0, 10)),
+      cells(&glider, (-10, -10)),
+    ]
+    .concat()
+    .into_iter()
+    .collect();
+    let objects = census(&pattern, &Rule::conway());
+    assert_eq!(objects.len(), 4);
+    assert_eq!(
+      tally(&objects),
+      vec![
+        (Some("xs4_33".to_string()), 2),
+        (Some("xp2_7".to_string()), 1),
+        (Some("xq4_153".to_string()), 1)
+      ]
+    );
+    let glider = objects
+      .iter()
+      .find(|object| object.apgcode.as_deref() == Some("xq4_153"))
+      .unwrap();
+    assert_eq!(
+      glider.periodicity,
+      Periodicity::Spaceship {
+        period: 4,
+        offset: (1, 1)
+      }
+    );
+  }
+
+  #[test]
+  fn keeps_interacting_cells_together() {
+    // A blinker next to a block only comes close to it in its vertical phase
+    let pattern: CellSet = [
+      cells(&[(0, 0), (1, 0), (0, 1), (1, 1)], (0, 0)),
+      cells(&[(0, 0), (1, 0), (2, 0)], (2, 4)),
+    ]
+    .concat()
+    .into_iter()
+    .collect();
+    let objects = census(&pattern, &Rule::conway());
+    assert_eq!(objects.len(), 1);
+  }
+
+  #[test]
+  fn leaves_transients_unidentified() {
+    let r_pentomino: CellSet = cells(&[(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)], (0, 0))
+      .into_iter()
+      .collect();
+    let objects = census(&r_pentomino, &Rule::conway());
+    assert_eq!(objects.len(), 1);
+    assert_eq!(objects[0].periodicity, Periodicity::NotYetPeriodic);
+    assert_eq!(objects[0].apgcode, None);
+  }
+
+  #[test]
+  fn round_trips_apgcodes() {
+    for apgcode in [
+      "xs4_33",
+      "xs7_2596",
+      "xp2_7e",
+      "xp15_4r4z4r4",
+      "xq4_153",
+      "xq4_27deee6",
+    ] {
+      let cells = crate::life::apgcode_cells(apgcode).unwrap();
+      assert_eq!(
+        apgcode_of(&cells, &Rule::conway()),
+        Some(apgcode.to_string())
+      );
+    }
+    let r_pentomino = cells(&[(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)], (0, 0));
+    assert_eq!(
+      apgcode_of(&r_pentomino.into_iter().collect(), &Rule::conway()),
+      None
+    );
+  }
+}
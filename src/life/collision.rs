@@ -0,0 +1,295 @@
+use crate::life::census::Object;
+use crate::life::engine::bounding_box;
+use crate::life::{census, common_name, tally, tick, CellSet, Periodicity, Rule};
+use lexicon::Cell;
+use std::ops::RangeInclusive;
+
+/// How long a collision is run for after the spaceships meet.
+const SETTLE_GENERATIONS: u64 = 500;
+
+/// How many generations a spaceship is run for to find its speed.
+const MAX_PERIOD: u64 = 64;
+
+/// The farthest a spaceship can be shifted from the lane that hits the
+/// target in the middle.
+pub const MAX_LANE: i32 = 100;
+
+/// The most generations a spaceship can be run for before being placed.
+pub const MAX_TIMING: u64 = 100;
+
+/// A spaceship sent towards the target, tried on every lane and timing of
+/// the ranges.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Incoming {
+  pub cells: CellSet,
+  pub lanes: RangeInclusive<i32>,
+  pub timings: RangeInclusive<u64>,
+}
+
+/// Where and when a spaceship comes: its lane is shifted sideways from the
+/// path that hits the target in the middle, and it is run for `timing`
+/// generations before being placed.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Setup {
+  pub lane: i32,
+  pub timing: u64,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Outcome {
+  /// How each incoming spaceship was placed.
+  pub setups: Vec<Setup>,
+  /// The spaceships before they meet.
+  pub start: CellSet,
+  /// Whether the spaceships affected each other at all.
+  pub interacted: bool,
+  /// What is left once things settle down.
+  pub objects: Vec<Object>,
+}
+
+impl Outcome {
+  pub fn summary(&self) -> String {
+    if !self.interacted {
+      return "no interaction".to_string();
+    }
+    if self.objects.is_empty() {
+      return "annihilation".to_string();
+    }
+    tally(&self.objects)
+      .into_iter()
+      .map(|(apgcode, count)| {
+        let name = match &apgcode {
+          Some(apgcode) => common_name(apgcode).unwrap_or(apgcode).to_string(),
+          None => "unsettled".to_string(),
+        };
+        format!("{} {}", count, name)
+      })
+      .collect::<Vec<_>>()
+      .join(", ")
+  }
+}
+
+/// The period of a spaceship and how far it goes in that time.
+fn velocity(cells: &CellSet, rule: &Rule) -> Option<(u64, (i32, i32))> {
+  let (object, _) = Object::analyse(cells.clone(), rule, MAX_PERIOD);
+  match object.periodicity {
+    Periodicity::Spaceship { period, offset } => Some((period, offset)),
+    _ => None,
+  }
+}
+
+fn run(cells: &CellSet, rule: &Rule, generations: u64) -> CellSet {
+  (0..generations).fold(cells.clone(), |cells, _| tick(&cells, rule))
+}
+
+fn translate(cells: &CellSet, (dx, dy): (i32, i32)) -> CellSet {
+  cells
+    .iter()
+    .map(|cell| Cell {
+      x: cell.x + dx,
+      y: cell.y + dy,
+    })
+    .collect()
+}
+
+/// The path of an incoming spaceship: where it starts from to hit the
+/// target in the middle, and which way its lanes are shifted.
+struct Path {
+  start: (i32, i32),
+  sideways: (i32, i32),
+  generations: u64,
+}
+
+fn path(target: &CellSet, ship: &Incoming, rule: &Rule, number: usize) -> Result<Path, String> {
+  let (target_period, target_offset) =
+    velocity(target, rule).ok_or("The first pattern isn’t a spaceship")?;
+  let (period, offset) =
+    velocity(&ship.cells, rule).ok_or(format!("Pattern {} isn’t a spaceship", number))?;
+  // How far the spaceship gets closer to the target, over both periods
+  let relative = (
+    offset.0 * target_period as i32 - target_offset.0 * period as i32,
+    offset.1 * target_period as i32 - target_offset.1 * period as i32,
+  );
+  if relative == (0, 0) {
+    return Err(format!(
+      "Spaceship {} goes in the same direction as the first one at the same speed",
+      number
+    ));
+  }
+  let size = |cells: &CellSet| {
+    bounding_box(cells.iter().copied()).map_or(0, |(top_left, bottom_right)| {
+      (bottom_right.x - top_left.x).max(bottom_right.y - top_left.y) + 1
+    })
+  };
+  // Far enough for the bounding boxes not to touch, whatever the lane
+  let lane = ship.lanes.start().abs().max(ship.lanes.end().abs());
+  let distance = size(target) + size(&ship.cells) + lane + 4;
+  let periods = distance / relative.0.abs().max(relative.1.abs()) + 1;
+  Ok(Path {
+    start: (-relative.0 * periods, -relative.1 * periods),
+    sideways: (-relative.1.signum(), relative.0.signum()),
+    generations: periods as u64 * target_period * period,
+  })
+}
+
+/// Crashes the incoming spaceships into the first one for every combination
+/// of their lanes and timings, and tells what comes out.
+pub fn collide(
+  target: &CellSet,
+  incoming: &[Incoming],
+  rule: &Rule,
+) -> Result<Vec<Outcome>, String> {
+  if incoming.is_empty() {
+    return Err("A collision needs at least two spaceships".to_string());
+  }
+  for ship in incoming {
+    let lanes = ship.lanes.start().abs().max(ship.lanes.end().abs());
+    if lanes > MAX_LANE || *ship.timings.end() > MAX_TIMING {
+      return Err(format!(
+        "Lanes go up to {} and timings up to {}",
+        MAX_LANE, MAX_TIMING
+      ));
+    }
+  }
+  let paths = incoming
+    .iter()
+    .enumerate()
+    .map(|(i, ship)| path(target, ship, rule, i + 2))
+    .collect::<Result<Vec<_>, _>>()?;
+  let generations = paths.iter().map(|path| path.generations).max().unwrap() + SETTLE_GENERATIONS;
+  let target_alone = run(target, rule, generations);
+  // Each spaceship in every phase it can be placed in, then run on its own
+  let phases: Vec<Vec<(CellSet, CellSet)>> = incoming
+    .iter()
+    .map(|ship| {
+      ship
+        .timings
+        .clone()
+        .map(|timing| {
+          let placed = run(&ship.cells, rule, timing);
+          let alone = run(&placed, rule, generations);
+          (placed, alone)
+        })
+        .collect()
+    })
+    .collect();
+
+  let mut combinations: Vec<Vec<Setup>> = vec![vec![]];
+  for ship in incoming {
+    combinations = combinations
+      .into_iter()
+      .flat_map(|setups| {
+        ship.lanes.clone().flat_map(move |lane| {
+          let setups = setups.clone();
+          ship.timings.clone().map(move |timing| {
+            let mut setups = setups.clone();
+            setups.push(Setup { lane, timing });
+            setups
+          })
+        })
+      })
+      .collect();
+  }
+
+  let mut outcomes = vec![];
+  for setups in combinations {
+    let mut start = target.clone();
+    let mut apart = target_alone.clone();
+    for (i, setup) in setups.iter().enumerate() {
+      let path = &paths[i];
+      let shift = (
+        path.start.0 + setup.lane * path.sideways.0,
+        path.start.1 + setup.lane * path.sideways.1,
+      );
+      let (placed, alone) = &phases[i][(setup.timing - incoming[i].timings.start()) as usize];
+      start.extend(translate(placed, shift));
+      apart.extend(translate(alone, shift));
+    }
+    let end = run(&start, rule, generations);
+    outcomes.push(Outcome {
+      setups,
+      interacted: end != apart,
+      objects: census(&end, rule),
+      start,
+    });
+  }
+  Ok(outcomes)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::life::orientations;
+
+  fn glider() -> CellSet {
+    [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]
+      .iter()
+      .map(|&(x, y)| Cell { x, y })
+      .collect()
+  }
+
+  #[test]
+  fn tabulates_head_on_glider_collisions() {
+    let rule = Rule::conway();
+    // Rotated by 180°, the glider goes up and left
+    let incoming = Incoming {
+      cells: orientations(&glider())[2].clone(),
+      lanes: -6..=6,
+      timings: 0..=1,
+    };
+    let outcomes = collide(&glider(), &[incoming], &rule).unwrap();
+    assert_eq!(outcomes.len(), 13 * 2);
+    let far = outcomes
+      .iter()
+      .find(|outcome| outcome.setups[0].lane == 6)
+      .unwrap();
+    assert!(!far.interacted);
+    assert_eq!(far.summary(), "no interaction");
+    assert!(outcomes
+      .iter()
+      .any(|outcome| outcome.summary() == "annihilation"));
+    assert!(outcomes
+      .iter()
+      .any(|outcome| outcome.summary().contains("block")));
+  }
+
+  #[test]
+  fn combines_several_spaceships() {
+    let rule = Rule::conway();
+    let incoming = [
+      Incoming {
+        cells: orientations(&glider())[2].clone(),
+        lanes: 0..=1,
+        timings: 0..=0,
+      },
+      Incoming {
+        cells: orientations(&glider())[1].clone(),
+        lanes: -1..=1,
+        timings: 0..=1,
+      },
+    ];
+    let outcomes = collide(&glider(), &incoming, &rule).unwrap();
+    assert_eq!(outcomes.len(), 2 * 3 * 2);
+    assert!(outcomes.iter().all(|outcome| outcome.setups.len() == 2));
+    assert!(outcomes.iter().all(|outcome| outcome.start.len() == 15));
+  }
+
+  #[test]
+  fn needs_spaceships_that_meet() {
+    let rule = Rule::conway();
+    let incoming = |cells: CellSet, lanes| Incoming {
+      cells,
+      lanes,
+      timings: 0..=0,
+    };
+    assert!(collide(&glider(), &[], &rule).is_err());
+    assert!(collide(&glider(), &[incoming(glider(), 0..=0)], &rule).is_err());
+    let block: CellSet = [(0, 0), (1, 0), (0, 1), (1, 1)]
+      .iter()
+      .map(|&(x, y)| Cell { x, y })
+      .collect();
+    assert!(collide(&block, &[incoming(glider(), 0..=0)], &rule).is_err());
+    let opposite = orientations(&glider())[2].clone();
+    assert!(collide(&glider(), &[incoming(opposite, 0..=MAX_LANE + 1)], &rule).is_err());
+  }
+}
@@ -0,0 +1,209 @@
+use crate::life::hashlife::{Block, HashLife};
+use crate::life::sparse::SparseLife;
+use crate::life::tiled::TiledLife;
+use crate::life::{CellStates, Rule};
+use lexicon::Cell;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// A rectangle of the universe, like the part visible on the board.
+pub type Area = (Range<i32>, Range<i32>);
+
+/// A way of storing and running a universe, where cells are dead (0) unless
+/// they were given another state.
+pub trait LifeEngine {
+  fn set_rule(&mut self, rule: &Rule);
+
+  /// Advances the universe by one generation.
+  fn step(&mut self);
+
+  /// Advances the universe by `n` generations, which some engines do much
+  /// faster than one at a time.
+  fn step_n(&mut self, n: u64) {
+    for _ in 0..n {
+      self.step();
+    }
+  }
+
+  fn get_cell(&self, cell: Cell) -> u8;
+
+  fn set_cell(&mut self, cell: Cell, state: u8);
+
+  fn population(&self) -> u64;
+
+  /// The top-left and bottom-right cells that aren't dead, if any.
+  fn bounding_box(&self) -> Option<(Cell, Cell)>;
+
+  /// The cells of the area that aren't dead, with their state.
+  fn viewport(&self, area: &Area) -> Box<dyn Iterator<Item = (Cell, u8)> + '_>;
+
+  /// All the cells that aren't dead.
+  fn cells(&self) -> CellStates {
+    match self.bounding_box() {
+      Some((top_left, bottom_right)) => self
+        .viewport(&(
+          top_left.x..bottom_right.x + 1,
+          top_left.y..bottom_right.y + 1,
+        ))
+        .collect(),
+      None => CellStates::new(),
+    }
+  }
+
+  /// The quadtree of the universe, for the engines that keep one.
+  fn quadtree(&self) -> Option<&HashLife> {
+    None
+  }
+
+  /// The blocks of 2^level cells of the area that aren't empty, with the
+  /// proportion of cells that aren't dead in each one.
+  fn blocks(&self, area: &Area, level: u8) -> Vec<Block> {
+    let mut populations: HashMap<(i64, i64), u64> = HashMap::new();
+    for (cell, _) in self.viewport(area) {
+      let key = ((cell.x as i64) >> level, (cell.y as i64) >> level);
+      *populations.entry(key).or_default() += 1;
+    }
+    let size = 1_u64 << level;
+    populations
+      .into_iter()
+      .map(|((x, y), population)| Block {
+        x: x << level,
+        y: y << level,
+        size,
+        density: population as f64 / (size * size) as f64,
+      })
+      .collect()
+  }
+}
+
+/// The bounding box of some cells.
+pub(crate) fn bounding_box(cells: impl Iterator<Item = Cell>) -> Option<(Cell, Cell)> {
+  cells.fold(None, |bounds, cell| match bounds {
+    None => Some((cell, cell)),
+    Some((top_left, bottom_right)) => Some((
+      Cell {
+        x: top_left.x.min(cell.x),
+        y: top_left.y.min(cell.y),
+      },
+      Cell {
+        x: bottom_right.x.max(cell.x),
+        y: bottom_right.y.max(cell.y),
+      },
+    )),
+  })
+}
+
+/// The engines to choose from.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Engine {
+  /// A hash map of the cells, which runs any rule.
+  Sparse,
+  /// Bitboard tiles, for dense patterns of totalistic rules.
+  Tiled,
+  /// A memoized quadtree, for huge patterns and jumps far ahead.
+  HashLife,
+}
+
+impl Engine {
+  pub const ALL: [Engine; 3] = [Engine::Sparse, Engine::Tiled, Engine::HashLife];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Engine::Sparse => "Sparse",
+      Engine::Tiled => "Tiled",
+      Engine::HashLife => "HashLife",
+    }
+  }
+
+  pub fn supports(&self, rule: &Rule) -> bool {
+    match self {
+      Engine::Sparse => true,
+      Engine::Tiled => rule.is_life_like() && rule.is_totalistic(),
+      Engine::HashLife => rule.is_life_like(),
+    }
+  }
+
+  /// The fastest engine to run a rule one generation at a time.
+  pub fn best_for(rule: &Rule) -> Engine {
+    if Engine::Tiled.supports(rule) {
+      Engine::Tiled
+    } else {
+      Engine::Sparse
+    }
+  }
+
+  pub fn create(&self, rule: &Rule, cells: &CellStates) -> Box<dyn LifeEngine> {
+    let mut engine: Box<dyn LifeEngine> = match self {
+      Engine::Sparse => Box::new(SparseLife::new(rule)),
+      Engine::Tiled => Box::new(TiledLife::new(rule)),
+      Engine::HashLife => Box::new(HashLife::new(rule)),
+    };
+    for (&cell, &state) in cells {
+      engine.set_cell(cell, state);
+    }
+    engine
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::life::from_alive_cells;
+
+  fn r_pentomino() -> CellStates {
+    from_alive_cells(
+      &[(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)]
+        .iter()
+        .map(|&(x, y)| Cell { x, y })
+        .collect(),
+    )
+  }
+
+  #[test]
+  fn engines_agree() {
+    let rule = Rule::conway();
+    let mut engines: Vec<Box<dyn LifeEngine>> = Engine::ALL
+      .iter()
+      .map(|engine| engine.create(&rule, &r_pentomino()))
+      .collect();
+    for engine in engines.iter_mut() {
+      engine.step_n(100);
+    }
+    let cells = engines[0].cells();
+    assert_eq!(cells.len(), 121);
+    for engine in &engines {
+      assert_eq!(engine.cells(), cells);
+      assert_eq!(engine.population(), 121);
+      assert_eq!(engine.bounding_box(), bounding_box(cells.keys().copied()));
+    }
+  }
+
+  #[test]
+  fn viewport_only_returns_the_area() {
+    for engine in Engine::ALL {
+      let engine = engine.create(&Rule::conway(), &r_pentomino());
+      let mut cells: Vec<Cell> = engine
+        .viewport(&(1..3, 0..2))
+        .map(|(cell, _)| cell)
+        .collect();
+      cells.sort_by_key(|cell| (cell.y, cell.x));
+      assert_eq!(
+        cells,
+        vec![
+          Cell { x: 1, y: 0 },
+          Cell { x: 2, y: 0 },
+          Cell { x: 1, y: 1 }
+        ]
+      );
+    }
+  }
+
+  #[test]
+  fn blocks_count_cells() {
+    let engine = Engine::Sparse.create(&Rule::conway(), &r_pentomino());
+    let mut blocks = engine.blocks(&(0..4, 0..4), 1);
+    blocks.sort_by_key(|block| (block.y, block.x));
+    let densities: Vec<f64> = blocks.iter().map(|block| block.density).collect();
+    assert_eq!(densities, vec![0.75, 0.25, 0.25]);
+  }
+}
@@ -0,0 +1,604 @@
+use crate::life::engine::{Area, LifeEngine};
+use crate::life::{CellSet, CellStates, Rule, NEIGHBORS};
+use lexicon::Cell;
+use std::collections::HashMap;
+
+pub(super) type NodeId = u32;
+
+/// The top-left and bottom-right cells that aren't dead, if any.
+type Bounds = Option<((i64, i64), (i64, i64))>;
+
+pub(super) const DEAD: NodeId = 0;
+pub(super) const ALIVE: NodeId = 1;
+
+/// Past this number of nodes, everything that isn't reachable from the root
+/// is dropped, along with the memoized results.
+const MAX_NODES: usize = 4_000_000;
+
+/// The largest power of two of generations jumped at once, past which the
+/// levels of the quadtree and the coordinates of its origin would overflow.
+pub const MAX_STEP_POW2: u8 = 48;
+
+/// A square of 2^level cells. Level 0 nodes are the dead and alive cells
+/// themselves, every other node is made of its four quadrants.
+#[derive(Clone, Copy)]
+pub(super) struct Node {
+  pub(super) level: u8,
+  pub(super) population: u64,
+  pub(super) nw: NodeId,
+  pub(super) ne: NodeId,
+  pub(super) sw: NodeId,
+  pub(super) se: NodeId,
+}
+
+/// A square area of the universe, as seen from far away.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Block {
+  pub x: i64,
+  pub y: i64,
+  pub size: u64,
+  pub density: f64,
+}
+
+/// A universe stored as a memoized quadtree, so it can be advanced by 2^n
+/// generations at once (see Gosper’s HashLife algorithm).
+pub struct HashLife {
+  pub(super) rule: Rule,
+  nodes: Vec<Node>,
+  index: HashMap<[NodeId; 4], NodeId>,
+  results: HashMap<(NodeId, u8), NodeId>,
+  empty: Vec<NodeId>,
+  pub(super) root: NodeId,
+  pub(super) origin: (i64, i64),
+}
+
+impl HashLife {
+  pub fn new(rule: &Rule) -> Self {
+    let leaf = |population| Node {
+      level: 0,
+      population,
+      nw: DEAD,
+      ne: DEAD,
+      sw: DEAD,
+      se: DEAD,
+    };
+    let mut universe = Self {
+      rule: rule.clone(),
+      nodes: vec![leaf(0), leaf(1)],
+      index: HashMap::new(),
+      results: HashMap::new(),
+      empty: vec![DEAD],
+      root: DEAD,
+      origin: (0, 0),
+    };
+    universe.root = universe.empty_node(3);
+    universe.origin = (-4, -4);
+    universe
+  }
+
+  pub fn from_cells(cells: &CellSet, rule: &Rule) -> Self {
+    let mut universe = Self::new(rule);
+    for &cell in cells {
+      universe.set_cell(cell, 1);
+    }
+    universe
+  }
+
+  /// Advances the universe by 2^n generations, `n` being at most
+  /// `MAX_STEP_POW2`.
+  pub fn step_pow2(&mut self, n: u8) {
+    assert!(
+      n <= MAX_STEP_POW2,
+      "Can’t jump more than 2^{} generations at once",
+      MAX_STEP_POW2
+    );
+    while self.node(self.root).level < n + 3 || !self.is_padded() {
+      self.expand();
+    }
+    let level = self.node(self.root).level;
+    self.root = self.successor(self.root, n);
+    let quarter = 1_i64 << (level - 2);
+    self.origin = (self.origin.0 + quarter, self.origin.1 + quarter);
+    if self.nodes.len() > MAX_NODES {
+      self.collect_garbage();
+    }
+  }
+
+  pub(super) fn node(&self, id: NodeId) -> Node {
+    self.nodes[id as usize]
+  }
+
+  fn contains(&self, x: i64, y: i64) -> bool {
+    let size = 1_i64 << self.node(self.root).level;
+    x >= self.origin.0 && x < self.origin.0 + size && y >= self.origin.1 && y < self.origin.1 + size
+  }
+
+  pub(super) fn join(&mut self, nw: NodeId, ne: NodeId, sw: NodeId, se: NodeId) -> NodeId {
+    let key = [nw, ne, sw, se];
+    if let Some(&id) = self.index.get(&key) {
+      return id;
+    }
+    let node = Node {
+      level: self.node(nw).level + 1,
+      population: key.iter().map(|&id| self.node(id).population).sum(),
+      nw,
+      ne,
+      sw,
+      se,
+    };
+    let id = self.nodes.len() as NodeId;
+    self.nodes.push(node);
+    self.index.insert(key, id);
+    id
+  }
+
+  pub(super) fn empty_node(&mut self, level: u8) -> NodeId {
+    while self.empty.len() <= level as usize {
+      let e = *self.empty.last().unwrap();
+      let id = self.join(e, e, e, e);
+      self.empty.push(id);
+    }
+    self.empty[level as usize]
+  }
+
+  /// Doubles the size of the universe, keeping the current root at its center.
+  pub(super) fn expand(&mut self) {
+    let root = self.node(self.root);
+    let e = self.empty_node(root.level - 1);
+    let nw = self.join(e, e, e, root.nw);
+    let ne = self.join(e, e, root.ne, e);
+    let sw = self.join(e, root.sw, e, e);
+    let se = self.join(root.se, e, e, e);
+    self.root = self.join(nw, ne, sw, se);
+    let quarter = 1_i64 << (root.level - 1);
+    self.origin = (self.origin.0 - quarter, self.origin.1 - quarter);
+  }
+
+  /// Whether all the cells are in the center sixteenth of the root, which
+  /// leaves enough room for them to evolve without leaving the universe.
+  fn is_padded(&self) -> bool {
+    let root = self.node(self.root);
+    let (nw, ne, sw, se) = (
+      self.node(root.nw),
+      self.node(root.ne),
+      self.node(root.sw),
+      self.node(root.se),
+    );
+    let inner = self.node(self.node(nw.se).se).population
+      + self.node(self.node(ne.sw).sw).population
+      + self.node(self.node(sw.ne).ne).population
+      + self.node(self.node(se.nw).nw).population;
+    inner == root.population
+  }
+
+  fn set_in(&mut self, id: NodeId, x: i64, y: i64, alive: bool) -> NodeId {
+    let node = self.node(id);
+    if node.level == 0 {
+      return if alive { ALIVE } else { DEAD };
+    }
+    let half = 1_i64 << (node.level - 1);
+    let (mut nw, mut ne, mut sw, mut se) = (node.nw, node.ne, node.sw, node.se);
+    match (x >= half, y >= half) {
+      (false, false) => nw = self.set_in(nw, x, y, alive),
+      (true, false) => ne = self.set_in(ne, x - half, y, alive),
+      (false, true) => sw = self.set_in(sw, x, y - half, alive),
+      (true, true) => se = self.set_in(se, x - half, y - half, alive),
+    }
+    self.join(nw, ne, sw, se)
+  }
+
+  fn collect_cells(&self, id: NodeId, x: i64, y: i64, cells: &mut CellStates) {
+    let node = self.node(id);
+    if node.population == 0 {
+      return;
+    }
+    if node.level == 0 {
+      cells.insert(
+        Cell {
+          x: x as i32,
+          y: y as i32,
+        },
+        1,
+      );
+      return;
+    }
+    let half = 1_i64 << (node.level - 1);
+    self.collect_cells(node.nw, x, y, cells);
+    self.collect_cells(node.ne, x + half, y, cells);
+    self.collect_cells(node.sw, x, y + half, cells);
+    self.collect_cells(node.se, x + half, y + half, cells);
+  }
+
+  fn collect_blocks(
+    &self,
+    id: NodeId,
+    (x, y): (i64, i64),
+    area @ ((from_x, from_y), (to_x, to_y)): ((i64, i64), (i64, i64)),
+    level: u8,
+    blocks: &mut Vec<Block>,
+  ) {
+    let node = self.node(id);
+    let size = 1_i64 << node.level;
+    if node.population == 0 || x > to_x || y > to_y || x + size <= from_x || y + size <= from_y {
+      return;
+    }
+    if node.level <= level {
+      blocks.push(Block {
+        x,
+        y,
+        size: size as u64,
+        density: node.population as f64 / (size * size) as f64,
+      });
+      return;
+    }
+    let half = size / 2;
+    self.collect_blocks(node.nw, (x, y), area, level, blocks);
+    self.collect_blocks(node.ne, (x + half, y), area, level, blocks);
+    self.collect_blocks(node.sw, (x, y + half), area, level, blocks);
+    self.collect_blocks(node.se, (x + half, y + half), area, level, blocks);
+  }
+
+  fn centered_horizontal(&mut self, w: NodeId, e: NodeId) -> NodeId {
+    let (w, e) = (self.node(w), self.node(e));
+    self.join(w.ne, e.nw, w.se, e.sw)
+  }
+
+  fn centered_vertical(&mut self, n: NodeId, s: NodeId) -> NodeId {
+    let (n, s) = (self.node(n), self.node(s));
+    self.join(n.sw, n.se, s.nw, s.ne)
+  }
+
+  fn centered(&mut self, id: NodeId) -> NodeId {
+    let node = self.node(id);
+    let (nw, ne, sw, se) = (
+      self.node(node.nw),
+      self.node(node.ne),
+      self.node(node.sw),
+      self.node(node.se),
+    );
+    self.join(nw.se, ne.sw, sw.ne, se.nw)
+  }
+
+  /// The center half of a 4x4 node, one generation later.
+  fn successor_base(&mut self, id: NodeId) -> NodeId {
+    let mut grid = [[false; 4]; 4];
+    let node = self.node(id);
+    for (quadrant, (qx, qy)) in [
+      (node.nw, (0, 0)),
+      (node.ne, (2, 0)),
+      (node.sw, (0, 2)),
+      (node.se, (2, 2)),
+    ] {
+      let quadrant = self.node(quadrant);
+      grid[qy][qx] = quadrant.nw == ALIVE;
+      grid[qy][qx + 1] = quadrant.ne == ALIVE;
+      grid[qy + 1][qx] = quadrant.sw == ALIVE;
+      grid[qy + 1][qx + 1] = quadrant.se == ALIVE;
+    }
+    let next = |x: i32, y: i32| {
+      let neighborhood = NEIGHBORS
+        .iter()
+        .enumerate()
+        .filter(|(_, &(dx, dy))| grid[(y + dy) as usize][(x + dx) as usize])
+        .fold(0, |neighborhood, (i, _)| neighborhood | 1 << i);
+      if self
+        .rule
+        .next_state(grid[y as usize][x as usize], neighborhood)
+      {
+        ALIVE
+      } else {
+        DEAD
+      }
+    };
+    let (nw, ne, sw, se) = (next(1, 1), next(2, 1), next(1, 2), next(2, 2));
+    self.join(nw, ne, sw, se)
+  }
+
+  /// The center half of a node, 2^n generations later (n being at most the
+  /// node level minus 2).
+  fn successor(&mut self, id: NodeId, n: u8) -> NodeId {
+    let node = self.node(id);
+    if node.population == 0 {
+      return self.empty_node(node.level - 1);
+    }
+    if node.level == 2 {
+      return self.successor_base(id);
+    }
+    if let Some(&result) = self.results.get(&(id, n)) {
+      return result;
+    }
+
+    let c00 = node.nw;
+    let c01 = self.centered_horizontal(node.nw, node.ne);
+    let c02 = node.ne;
+    let c10 = self.centered_vertical(node.nw, node.sw);
+    let c11 = self.centered(id);
+    let c12 = self.centered_vertical(node.ne, node.se);
+    let c20 = node.sw;
+    let c21 = self.centered_horizontal(node.sw, node.se);
+    let c22 = node.se;
+
+    // At full speed, both halves of the 2^n generations are computed
+    // recursively, otherwise the first half is skipped.
+    let full_speed = n == node.level - 2;
+    let advance = |universe: &mut Self, id| {
+      if full_speed {
+        universe.successor(id, n - 1)
+      } else {
+        universe.centered(id)
+      }
+    };
+    let r00 = advance(self, c00);
+    let r01 = advance(self, c01);
+    let r02 = advance(self, c02);
+    let r10 = advance(self, c10);
+    let r11 = advance(self, c11);
+    let r12 = advance(self, c12);
+    let r20 = advance(self, c20);
+    let r21 = advance(self, c21);
+    let r22 = advance(self, c22);
+
+    let remaining = if full_speed { n - 1 } else { n };
+    let nw = self.join(r00, r01, r10, r11);
+    let ne = self.join(r01, r02, r11, r12);
+    let sw = self.join(r10, r11, r20, r21);
+    let se = self.join(r11, r12, r21, r22);
+    let nw = self.successor(nw, remaining);
+    let ne = self.successor(ne, remaining);
+    let sw = self.successor(sw, remaining);
+    let se = self.successor(se, remaining);
+    let result = self.join(nw, ne, sw, se);
+
+    self.results.insert((id, n), result);
+    result
+  }
+
+  /// The top-left and bottom-right cells of a node that aren't dead,
+  /// relative to its own top-left corner. Nodes appearing many times are
+  /// only looked at once.
+  fn bounds(&self, id: NodeId, known: &mut HashMap<NodeId, Bounds>) -> Bounds {
+    if let Some(&bounds) = known.get(&id) {
+      return bounds;
+    }
+    let node = self.node(id);
+    if node.population == 0 {
+      return None;
+    }
+    if node.level == 0 {
+      return Some(((0, 0), (0, 0)));
+    }
+    let half = 1_i64 << (node.level - 1);
+    let bounds = [
+      (node.nw, 0, 0),
+      (node.ne, half, 0),
+      (node.sw, 0, half),
+      (node.se, half, half),
+    ]
+    .iter()
+    .filter_map(|&(quadrant, dx, dy)| {
+      let ((left, top), (right, bottom)) = self.bounds(quadrant, known)?;
+      Some(((left + dx, top + dy), (right + dx, bottom + dy)))
+    })
+    .reduce(
+      |(top_left, bottom_right), (other_top_left, other_bottom_right)| {
+        (
+          (
+            top_left.0.min(other_top_left.0),
+            top_left.1.min(other_top_left.1),
+          ),
+          (
+            bottom_right.0.max(other_bottom_right.0),
+            bottom_right.1.max(other_bottom_right.1),
+          ),
+        )
+      },
+    );
+    known.insert(id, bounds);
+    bounds
+  }
+
+  /// Rebuilds the node arena with only the nodes reachable from the root.
+  fn collect_garbage(&mut self) {
+    let mut fresh = Self::new(&self.rule);
+    let mut copied = HashMap::new();
+    copied.insert(DEAD, DEAD);
+    copied.insert(ALIVE, ALIVE);
+    fresh.root = fresh.copy_from(self, self.root, &mut copied);
+    fresh.origin = self.origin;
+    *self = fresh;
+  }
+
+  fn copy_from(
+    &mut self,
+    other: &Self,
+    id: NodeId,
+    copied: &mut HashMap<NodeId, NodeId>,
+  ) -> NodeId {
+    if let Some(&copy) = copied.get(&id) {
+      return copy;
+    }
+    let node = other.node(id);
+    let nw = self.copy_from(other, node.nw, copied);
+    let ne = self.copy_from(other, node.ne, copied);
+    let sw = self.copy_from(other, node.sw, copied);
+    let se = self.copy_from(other, node.se, copied);
+    let copy = self.join(nw, ne, sw, se);
+    copied.insert(id, copy);
+    copy
+  }
+}
+
+impl LifeEngine for HashLife {
+  /// Changing the rule keeps the cells, but not the memoized results.
+  fn set_rule(&mut self, rule: &Rule) {
+    self.rule = rule.clone();
+    self.results.clear();
+  }
+
+  fn step(&mut self) {
+    self.step_pow2(0);
+  }
+
+  /// Jumps by each power of two that makes up `n`, the ones above
+  /// `MAX_STEP_POW2` being made of several of the largest jumps.
+  fn step_n(&mut self, n: u64) {
+    for i in 0..=MAX_STEP_POW2 {
+      if n & 1 << i != 0 {
+        self.step_pow2(i);
+      }
+    }
+    for _ in 0..(n >> (MAX_STEP_POW2 + 1)) << 1 {
+      self.step_pow2(MAX_STEP_POW2);
+    }
+  }
+
+  fn get_cell(&self, cell: Cell) -> u8 {
+    let (x, y) = (cell.x as i64, cell.y as i64);
+    if !self.contains(x, y) {
+      return 0;
+    }
+    let mut id = self.root;
+    let (mut x, mut y) = (x - self.origin.0, y - self.origin.1);
+    while self.node(id).level > 0 {
+      let node = self.node(id);
+      let half = 1_i64 << (node.level - 1);
+      id = match (x >= half, y >= half) {
+        (false, false) => node.nw,
+        (true, false) => node.ne,
+        (false, true) => node.sw,
+        (true, true) => node.se,
+      };
+      x %= half;
+      y %= half;
+    }
+    (id == ALIVE) as u8
+  }
+
+  /// Every state other than 0 is alive.
+  fn set_cell(&mut self, cell: Cell, state: u8) {
+    let (x, y) = (cell.x as i64, cell.y as i64);
+    while !self.contains(x, y) {
+      self.expand();
+    }
+    let (x, y) = (x - self.origin.0, y - self.origin.1);
+    self.root = self.set_in(self.root, x, y, state != 0);
+  }
+
+  fn population(&self) -> u64 {
+    self.node(self.root).population
+  }
+
+  fn bounding_box(&self) -> Option<(Cell, Cell)> {
+    let ((left, top), (right, bottom)) = self.bounds(self.root, &mut HashMap::new())?;
+    let cell = |x: i64, y: i64| Cell {
+      x: (self.origin.0 + x) as i32,
+      y: (self.origin.1 + y) as i32,
+    };
+    Some((cell(left, top), cell(right, bottom)))
+  }
+
+  fn viewport(&self, area: &Area) -> Box<dyn Iterator<Item = (Cell, u8)> + '_> {
+    Box::new(self.blocks(area, 0).into_iter().map(|block| {
+      let cell = Cell {
+        x: block.x as i32,
+        y: block.y as i32,
+      };
+      (cell, 1)
+    }))
+  }
+
+  /// All the alive cells. Only use this on patterns that fit in memory once
+  /// expanded.
+  fn cells(&self) -> CellStates {
+    let mut cells = CellStates::new();
+    self.collect_cells(self.root, self.origin.0, self.origin.1, &mut cells);
+    cells
+  }
+
+  fn quadtree(&self) -> Option<&HashLife> {
+    Some(self)
+  }
+
+  fn blocks(&self, (x, y): &Area, level: u8) -> Vec<Block> {
+    let area = (
+      (x.start as i64, y.start as i64),
+      (x.end as i64 - 1, y.end as i64 - 1),
+    );
+    let mut blocks = vec![];
+    self.collect_blocks(self.root, self.origin, area, level, &mut blocks);
+    blocks
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::life::{alive_cells, tick};
+
+  fn r_pentomino() -> CellSet {
+    [(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)]
+      .iter()
+      .map(|&(x, y)| Cell { x, y })
+      .collect()
+  }
+
+  #[test]
+  fn round_trips_cells() {
+    let universe = HashLife::from_cells(&r_pentomino(), &Rule::conway());
+    assert_eq!(alive_cells(&universe.cells()), r_pentomino());
+    assert_eq!(universe.population(), 5);
+  }
+
+  #[test]
+  fn single_step_matches_tick() {
+    let rule: Rule = "B36/S23".parse().unwrap();
+    let mut cells = r_pentomino();
+    let mut universe = HashLife::from_cells(&cells, &rule);
+    for _ in 0..50 {
+      cells = tick(&cells, &rule);
+      universe.step_pow2(0);
+      assert_eq!(alive_cells(&universe.cells()), cells);
+    }
+  }
+
+  #[test]
+  fn runs_isotropic_rules() {
+    let rule: Rule = "B2-a/S12".parse().unwrap();
+    let mut cells = r_pentomino();
+    let mut universe = HashLife::from_cells(&cells, &rule);
+    for _ in 0..16 {
+      cells = tick(&cells, &rule);
+    }
+    universe.step_pow2(4);
+    assert_eq!(alive_cells(&universe.cells()), cells);
+  }
+
+  #[test]
+  fn jump_matches_tick() {
+    let rule = Rule::conway();
+    let mut cells = r_pentomino();
+    for _ in 0..256 {
+      cells = tick(&cells, &rule);
+    }
+    let mut universe = HashLife::from_cells(&r_pentomino(), &rule);
+    universe.step_pow2(8);
+    assert_eq!(alive_cells(&universe.cells()), cells);
+  }
+
+  #[test]
+  fn jumps_as_far_as_asked() {
+    let block: CellSet = [(0, 0), (1, 0), (0, 1), (1, 1)]
+      .iter()
+      .map(|&(x, y)| Cell { x, y })
+      .collect();
+    let mut universe = HashLife::from_cells(&block, &Rule::conway());
+    universe.step_n(u64::MAX);
+    assert_eq!(alive_cells(&universe.cells()), block);
+  }
+
+  #[test]
+  #[should_panic]
+  fn refuses_too_large_jumps() {
+    HashLife::new(&Rule::conway()).step_pow2(MAX_STEP_POW2 + 1);
+  }
+}
@@ -0,0 +1,251 @@
+use crate::life::ParseRuleError;
+use std::fmt;
+
+/// Letters of Hensel’s notation, for each number of alive neighbors.
+const LETTERS: [&str; 9] = [
+  "",
+  "ce",
+  "ceaikn",
+  "ceaiknjqry",
+  "ceaiknjqrtwyz",
+  "ceaiknjqry",
+  "ceaikn",
+  "ce",
+  "",
+];
+
+/// One neighborhood for each letter of `LETTERS` with up to four alive
+/// neighbors (the others are their complements).
+const NEIGHBORHOODS: [&[u8]; 5] = [
+  &[0b0000_0000],
+  &[0b0000_0010, 0b0000_0001],
+  &[
+    0b0000_1010,
+    0b0000_0101,
+    0b0000_0011,
+    0b0001_0001,
+    0b0000_1001,
+    0b0010_0010,
+  ],
+  &[
+    0b0010_1010,
+    0b0001_0101,
+    0b0000_0111,
+    0b1000_0011,
+    0b0010_0101,
+    0b0000_1011,
+    0b0100_0011,
+    0b0010_0011,
+    0b0001_0011,
+    0b0010_1001,
+  ],
+  &[
+    0b1010_1010,
+    0b0101_0101,
+    0b0000_1111,
+    0b0001_1011,
+    0b0100_1011,
+    0b1000_1011,
+    0b0101_0011,
+    0b0010_0111,
+    0b0001_0111,
+    0b0011_1001,
+    0b0110_0011,
+    0b0010_1011,
+    0b0011_0011,
+  ],
+];
+
+/// The neighborhood of a given letter, as a bit mask of its alive neighbors
+/// clockwise from the north one.
+fn neighborhood(count: usize, letter: usize) -> u8 {
+  if count <= 4 {
+    NEIGHBORHOODS[count][letter]
+  } else {
+    !NEIGHBORHOODS[8 - count][letter]
+  }
+}
+
+/// The eight rotations and reflections of a neighborhood.
+fn symmetries(neighborhood: u8) -> Vec<u8> {
+  let reflected = (0..8)
+    .filter(|&i| neighborhood & (1 << i) != 0)
+    .fold(0, |reflected, i| reflected | 1 << ((8 - i) % 8));
+  [neighborhood, reflected]
+    .iter()
+    .flat_map(|&n| (0..4).map(move |quarter| n.rotate_left(2 * quarter)))
+    .collect()
+}
+
+/// A set of neighborhoods, each one being the bit mask of the alive neighbors
+/// of a cell, clockwise from the north one. It is always closed under
+/// rotations and reflections.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Neighborhoods([u64; 4]);
+
+impl Neighborhoods {
+  pub fn from_counts(counts: &[usize]) -> Self {
+    let mut neighborhoods = Self([0; 4]);
+    for mask in 0..=255_u8 {
+      if counts.contains(&(mask.count_ones() as usize)) {
+        neighborhoods.insert(mask);
+      }
+    }
+    neighborhoods
+  }
+
+  pub fn contains(&self, neighborhood: u8) -> bool {
+    self.0[neighborhood as usize / 64] & 1 << (neighborhood % 64) != 0
+  }
+
+  fn insert(&mut self, neighborhood: u8) {
+    self.0[neighborhood as usize / 64] |= 1 << (neighborhood % 64);
+  }
+
+  /// Whether all the neighborhoods with that many alive neighbors are in the
+  /// set.
+  pub fn contains_count(&self, count: usize) -> bool {
+    (0..=255_u8)
+      .filter(|mask| mask.count_ones() as usize == count)
+      .all(|mask| self.contains(mask))
+  }
+
+  /// Whether the set only depends on the number of alive neighbors.
+  pub fn is_totalistic(&self) -> bool {
+    (0..=255_u8).all(|mask| self.contains(mask) == self.contains_count(mask.count_ones() as usize))
+  }
+
+  fn insert_letter(&mut self, count: usize, letter: usize) {
+    for mask in symmetries(neighborhood(count, letter)) {
+      self.insert(mask);
+    }
+  }
+
+  fn contains_letter(&self, count: usize, letter: usize) -> bool {
+    self.contains(neighborhood(count, letter))
+  }
+
+  /// Parses Hensel’s notation, like "2-a" or "34q": each digit can be
+  /// followed by the letters of the neighborhoods to include, or by a minus
+  /// sign and the letters of the neighborhoods to exclude.
+  pub fn parse(notation: &str) -> Result<Self, ParseRuleError> {
+    let mut neighborhoods = Self([0; 4]);
+    let mut chars = notation.chars().peekable();
+    while let Some(c) = chars.next() {
+      let count = match c.to_digit(10) {
+        Some(digit) if digit <= 8 => digit as usize,
+        _ => {
+          return Err(ParseRuleError(format!(
+            "Invalid neighbor count '{}', expected a digit from 0 to 8",
+            c
+          )))
+        }
+      };
+      let excluding = chars.next_if_eq(&'-').is_some();
+      let mut letters = vec![];
+      while let Some(letter) = chars.next_if(|c| c.is_ascii_alphabetic()) {
+        match LETTERS[count].find(letter.to_ascii_lowercase()) {
+          Some(letter) => letters.push(letter),
+          None => {
+            return Err(ParseRuleError(format!(
+              "Invalid letter '{}' after {}, expected one of \"{}\"",
+              letter, count, LETTERS[count]
+            )))
+          }
+        }
+      }
+      for letter in 0..LETTERS[count].len().max(1) {
+        if letters.is_empty() || letters.contains(&letter) != excluding {
+          neighborhoods.insert_letter(count, letter);
+        }
+      }
+    }
+    Ok(neighborhoods)
+  }
+}
+
+impl fmt::Display for Neighborhoods {
+  /// Writes each count with the shortest list of letters, either included
+  /// or excluded.
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for (count, letters) in LETTERS.iter().enumerate() {
+      let included: Vec<bool> = (0..letters.len().max(1))
+        .map(|letter| self.contains_letter(count, letter))
+        .collect();
+      if !included.contains(&true) {
+        continue;
+      }
+      write!(f, "{}", count)?;
+      if included.contains(&false) {
+        let letters_where = |value: bool| -> String {
+          letters
+            .chars()
+            .zip(&included)
+            .filter(|&(_, &included)| included == value)
+            .map(|(letter, _)| letter)
+            .collect()
+        };
+        let (included, excluded) = (letters_where(true), letters_where(false));
+        if included.len() <= excluded.len() {
+          write!(f, "{}", included)?;
+        } else {
+          write!(f, "-{}", excluded)?;
+        }
+      }
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn letters_cover_every_neighborhood_once() {
+    let mut covered = [0; 256];
+    for (count, letters) in LETTERS.iter().enumerate() {
+      for letter in 0..letters.len().max(1) {
+        let mut letter_neighborhoods = symmetries(neighborhood(count, letter));
+        letter_neighborhoods.sort_unstable();
+        letter_neighborhoods.dedup();
+        for mask in letter_neighborhoods {
+          assert_eq!(mask.count_ones() as usize, count);
+          covered[mask as usize] += 1;
+        }
+      }
+    }
+    assert!(covered.iter().all(|&times| times == 1))
+  }
+
+  #[test]
+  fn parses_totalistic_counts() {
+    let neighborhoods = Neighborhoods::parse("23").unwrap();
+    assert_eq!(neighborhoods, Neighborhoods::from_counts(&[2, 3]));
+    assert!(neighborhoods.is_totalistic())
+  }
+
+  #[test]
+  fn parses_excluded_letters() {
+    let neighborhoods = Neighborhoods::parse("2-a").unwrap();
+    assert!(!neighborhoods.is_totalistic());
+    assert!(!neighborhoods.contains(0b0000_0011));
+    assert!(!neighborhoods.contains(0b1100_0000));
+    assert!(neighborhoods.contains(0b0001_0001));
+    assert_eq!(neighborhoods.to_string(), "2-a")
+  }
+
+  #[test]
+  fn formats_shortest_letters() {
+    let neighborhoods = Neighborhoods::parse("2-i34q").unwrap();
+    assert_eq!(neighborhoods.to_string(), "2-i34q");
+    let neighborhoods = Neighborhoods::parse("3aceiknjqy").unwrap();
+    assert_eq!(neighborhoods.to_string(), "3-r")
+  }
+
+  #[test]
+  fn rejects_invalid_letters() {
+    assert!(Neighborhoods::parse("1a").is_err());
+    assert!(Neighborhoods::parse("4x").is_err())
+  }
+}
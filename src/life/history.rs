@@ -0,0 +1,150 @@
+use crate::life::engine::bounding_box;
+use crate::life::{CellSet, CellStates};
+use lexicon::Cell;
+use std::collections::VecDeque;
+
+/// How many generations are kept, the oldest ones being dropped first.
+pub const MAX_RECORDS: usize = 10_000;
+
+/// Measurements of one generation. Births and deaths are counted since the
+/// previously recorded generation.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Record {
+  pub generation: u64,
+  pub population: u64,
+  pub bounding_box: Option<(Cell, Cell)>,
+  pub births: u64,
+  pub deaths: u64,
+}
+
+impl Record {
+  pub fn width(&self) -> u32 {
+    self.bounding_box.map_or(0, |(top_left, bottom_right)| {
+      (bottom_right.x - top_left.x + 1) as u32
+    })
+  }
+
+  pub fn height(&self) -> u32 {
+    self.bounding_box.map_or(0, |(top_left, bottom_right)| {
+      (bottom_right.y - top_left.y + 1) as u32
+    })
+  }
+}
+
+/// The time series of a simulation, generation after generation, up to the
+/// last `MAX_RECORDS` ones.
+#[derive(Default)]
+pub struct History {
+  records: VecDeque<Record>,
+  alive: CellSet,
+}
+
+impl History {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn records(&self) -> &VecDeque<Record> {
+    &self.records
+  }
+
+  /// Copies the last records, at most `count` of them.
+  pub fn latest(&self, count: usize) -> Vec<Record> {
+    let skipped = self.records.len().saturating_sub(count);
+    self.records.iter().skip(skipped).cloned().collect()
+  }
+
+  /// Adds a generation. Cells are born when they enter state 1 and die when
+  /// they leave it.
+  pub fn record(&mut self, generation: u64, cells: &CellStates) {
+    let alive: CellSet = cells
+      .iter()
+      .filter(|(_, &state)| state == 1)
+      .map(|(&cell, _)| cell)
+      .collect();
+    let (births, deaths) = if self.records.is_empty() {
+      (0, 0)
+    } else {
+      (
+        alive.difference(&self.alive).count() as u64,
+        self.alive.difference(&alive).count() as u64,
+      )
+    };
+    if self.records.len() == MAX_RECORDS {
+      self.records.pop_front();
+    }
+    self.records.push_back(Record {
+      generation,
+      population: cells.len() as u64,
+      bounding_box: bounding_box(cells.keys().copied()),
+      births,
+      deaths,
+    });
+    self.alive = alive;
+  }
+
+  pub fn to_csv(&self) -> String {
+    let mut csv = String::from("generation,population,left,top,width,height,births,deaths\n");
+    for record in &self.records {
+      let (left, top) = record
+        .bounding_box
+        .map_or((String::new(), String::new()), |(top_left, _)| {
+          (top_left.x.to_string(), top_left.y.to_string())
+        });
+      csv.push_str(&format!(
+        "{},{},{},{},{},{},{},{}\n",
+        record.generation,
+        record.population,
+        left,
+        top,
+        record.width(),
+        record.height(),
+        record.births,
+        record.deaths
+      ));
+    }
+    csv
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::life::{from_alive_cells, tick, Rule};
+
+  #[test]
+  fn records_births_and_deaths() {
+    let mut cells = [(0, 0), (1, 0), (2, 0)]
+      .iter()
+      .map(|&(x, y)| Cell { x, y })
+      .collect();
+    let mut history = History::new();
+    for generation in 0..2 {
+      history.record(generation, &from_alive_cells(&cells));
+      cells = tick(&cells, &Rule::conway());
+    }
+    let blinker = &history.records()[1];
+    assert_eq!(blinker.population, 3);
+    assert_eq!((blinker.births, blinker.deaths), (2, 2));
+    assert_eq!((blinker.width(), blinker.height()), (1, 3));
+    assert_eq!(
+      history.to_csv(),
+      "generation,population,left,top,width,height,births,deaths\n\
+       0,3,0,0,3,1,0,0\n\
+       1,3,1,-1,1,3,2,2\n"
+    );
+    assert_eq!(history.latest(1), vec![blinker.clone()]);
+  }
+
+  #[test]
+  fn drops_the_oldest_records() {
+    let cells = from_alive_cells(&[Cell { x: 0, y: 0 }].iter().copied().collect());
+    let mut history = History::new();
+    for generation in 0..MAX_RECORDS as u64 + 5 {
+      history.record(generation, &cells);
+    }
+    assert_eq!(history.records().len(), MAX_RECORDS);
+    assert_eq!(history.records()[0].generation, 5);
+    assert_eq!(history.to_csv().lines().count(), MAX_RECORDS + 1);
+  }
+}
@@ -0,0 +1,137 @@
+use crate::life::apgcode::{canonical_wechsler, common_name};
+use crate::life::census::Object;
+use crate::life::{CellSet, Rule};
+use lexicon::Term;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// How many generations a pattern is run for to find all its phases.
+const MAX_GENERATIONS: u64 = 128;
+
+/// Lexicon patterns bigger than this aren’t indexed, as running them would
+/// take too long.
+const MAX_CELLS: usize = 400;
+
+/// The same string for all the phases, rotations and reflections of a
+/// pattern: its apgcode if it is periodic, otherwise the canonical Wechsler
+/// code of its current phase prefixed with `xx_`.
+pub fn canonical_form(cells: &CellSet, rule: &Rule) -> String {
+  let (object, _) = Object::analyse(cells.clone(), rule, MAX_GENERATIONS);
+  object
+    .apgcode
+    .unwrap_or_else(|| format!("xx_{}", canonical_wechsler(std::slice::from_ref(cells))))
+}
+
+/// The names of the lexicon patterns, by canonical form under Conway’s
+/// Life.
+pub struct LexiconIndex {
+  names: HashMap<String, Vec<String>>,
+  /// The lexicon patterns that are spaceships, shared with whoever sends
+  /// them into collisions.
+  spaceships: Rc<Vec<(String, CellSet)>>,
+}
+
+impl LexiconIndex {
+  pub fn new(terms: &[Term]) -> Self {
+    let rule = Rule::conway();
+    let mut names: HashMap<String, Vec<String>> = HashMap::new();
+    let mut spaceships = vec![];
+    for term in terms {
+      if term.cells.is_empty() || term.cells.len() > MAX_CELLS {
+        continue;
+      }
+      let cells = term.cells.iter().copied().collect();
+      let form = canonical_form(&cells, &rule);
+      if form.starts_with("xq") {
+        spaceships.push((term.name.clone(), cells));
+      }
+      names.entry(form).or_default().push(term.name.clone());
+    }
+    Self {
+      names,
+      spaceships: Rc::new(spaceships),
+    }
+  }
+
+  /// The names of the lexicon patterns the cells are a phase of, up to
+  /// rotations and reflections.
+  pub fn identify(&self, cells: &CellSet) -> Option<&[String]> {
+    self.names_of(&canonical_form(cells, &Rule::conway()))
+  }
+
+  pub fn names_of(&self, canonical_form: &str) -> Option<&[String]> {
+    self.names.get(canonical_form).map(Vec::as_slice)
+  }
+
+  pub fn spaceships(&self) -> Rc<Vec<(String, CellSet)>> {
+    Rc::clone(&self.spaceships)
+  }
+
+  /// What an object with this apgcode is called: its first lexicon name, or
+  /// its usual name for the few objects the lexicon lacks.
+  pub fn name(&self, apgcode: &str) -> Option<&str> {
+    self
+      .names_of(apgcode)
+      .and_then(<[String]>::first)
+      .map(String::as_str)
+      .or_else(|| common_name(apgcode))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::life::orientations;
+  use lexicon::Cell;
+
+  fn cells(cells: &[(i32, i32)]) -> Vec<Cell> {
+    cells.iter().map(|&(x, y)| Cell { x, y }).collect()
+  }
+
+  fn term(name: &str, cells: Vec<Cell>) -> Term {
+    Term {
+      name: name.to_string(),
+      description: String::new(),
+      tags: vec![],
+      width: 0,
+      height: 0,
+      cells,
+    }
+  }
+
+  #[test]
+  fn identifies_all_phases_and_orientations() {
+    let index = LexiconIndex::new(&[
+      term("blinker", cells(&[(0, 0), (1, 0), (2, 0)])),
+      term("glider", cells(&[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)])),
+      term(
+        "R-pentomino",
+        cells(&[(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)]),
+      ),
+      term("empty", vec![]),
+    ]);
+    let vertical_blinker = cells(&[(5, 5), (5, 6), (5, 7)]).into_iter().collect();
+    assert_eq!(
+      index.identify(&vertical_blinker),
+      Some(&["blinker".to_string()][..])
+    );
+    let glider_phase = cells(&[(0, 0), (2, 0), (1, 1), (2, 1), (1, 2)]);
+    for glider in orientations(&glider_phase.into_iter().collect()) {
+      assert_eq!(index.identify(&glider), Some(&["glider".to_string()][..]));
+    }
+    let r_pentomino = cells(&[(0, 0), (1, 0), (1, 1), (2, 1), (1, 2)]);
+    assert_eq!(
+      index.identify(&r_pentomino.into_iter().collect()),
+      Some(&["R-pentomino".to_string()][..])
+    );
+    let block = cells(&[(0, 0), (1, 0), (0, 1), (1, 1)]);
+    assert_eq!(index.identify(&block.into_iter().collect()), None);
+    assert_eq!(index.names_of("xp2_7"), Some(&["blinker".to_string()][..]));
+    assert_eq!(index.name("xp2_7"), Some("blinker"));
+    let spaceships = index.spaceships();
+    assert_eq!(spaceships.len(), 1);
+    assert_eq!(spaceships[0].0, "glider");
+    assert_eq!(index.name("xs4_33"), Some("block"));
+    assert_eq!(index.name("xs5_255"), None);
+  }
+}
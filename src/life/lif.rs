@@ -0,0 +1,214 @@
+use crate::life::{ParsePatternError, Pattern};
+use lexicon::Cell;
+
+/// A rule written `S/B` in Life 1.05 files, like `23/3`, in B/S notation.
+fn rule_from_survival_birth(rule: &str) -> Option<String> {
+  let (survival, birth) = rule.split_once('/')?;
+  let digits = |counts: &str| counts.chars().all(|c| c.is_ascii_digit());
+  (digits(survival) && digits(birth)).then(|| format!("B{}/S{}", birth, survival))
+}
+
+/// A rule in B/S notation, like `B3/S23`, written `S/B` as in Life 1.05
+/// files, if it only has counts.
+fn rule_to_survival_birth(rule: &str) -> Option<String> {
+  let (birth, survival) = rule.split_once('/')?;
+  let birth = birth.strip_prefix('B')?;
+  let survival = survival.strip_prefix('S')?;
+  let digits = |counts: &str| counts.chars().all(|c| c.is_ascii_digit());
+  (digits(survival) && digits(birth)).then(|| format!("{}/{}", survival, birth))
+}
+
+impl Pattern {
+  /// Reads a pattern in the Life 1.05 format: `#D` description lines, the
+  /// rule as `#N` for Conway’s Life or `#R` followed by `S/B`, then blocks of
+  /// `.` and `*` rows, each starting with the `#P` position of its top left
+  /// corner.
+  pub fn from_life_105(text: &str) -> Result<Self, ParsePatternError> {
+    let mut pattern = Pattern::default();
+    let (mut left, mut y) = (0, 0);
+    for line in text.lines().map(str::trim) {
+      if line.is_empty() || line.starts_with("#Life") {
+        continue;
+      }
+      if let Some(line) = line.strip_prefix('#') {
+        let (tag, value) = line.split_at(line.chars().next().map_or(0, char::len_utf8));
+        let value = value.trim();
+        match tag {
+          "D" | "C" => pattern.comments.push(value.to_string()),
+          "N" => pattern.rule = Some("B3/S23".to_string()),
+          "R" => {
+            pattern.rule = Some(
+              rule_from_survival_birth(value)
+                .ok_or_else(|| ParsePatternError(format!("Invalid Life 1.05 rule '{}'", value)))?,
+            )
+          }
+          "P" => {
+            let coordinates: Vec<i32> = value
+              .split_whitespace()
+              .map(|coordinate| coordinate.parse())
+              .collect::<Result<_, _>>()
+              .map_err(|_| ParsePatternError(format!("Invalid Life 1.05 position '{}'", value)))?;
+            match coordinates[..] {
+              [x, top] => {
+                left = x;
+                y = top;
+              }
+              _ => {
+                return Err(ParsePatternError(format!(
+                  "Invalid Life 1.05 position '{}'",
+                  value
+                )))
+              }
+            }
+          }
+          _ => {}
+        }
+        continue;
+      }
+      for (x, c) in line.chars().enumerate() {
+        match c {
+          '.' => {}
+          '*' => {
+            pattern.cells.insert(
+              Cell {
+                x: left + x as i32,
+                y,
+              },
+              1,
+            );
+          }
+          _ => {
+            return Err(ParsePatternError(format!(
+              "Unexpected '{}' in Life 1.05",
+              c
+            )))
+          }
+        }
+      }
+      y += 1;
+    }
+    Ok(pattern)
+  }
+
+  /// Writes the alive cells of the pattern in the Life 1.05 format, as a
+  /// single block. The name goes in the description.
+  pub fn to_life_105(&self) -> String {
+    let mut text = String::from("#Life 1.05\n");
+    for description in self.name.iter().chain(self.comments.iter()) {
+      text.push_str(&format!("#D {}\n", description));
+    }
+    match self.rule.as_deref().and_then(rule_to_survival_birth) {
+      Some(rule) if rule == "23/3" => text.push_str("#N\n"),
+      Some(rule) => text.push_str(&format!("#R {}\n", rule)),
+      None => {}
+    }
+    if let Some((top_left, _)) = self.bounding_box() {
+      text.push_str(&format!("#P {} {}\n", top_left.x, top_left.y));
+      for row in self.rows() {
+        let line: String = row
+          .iter()
+          .map(|&state| if state == 1 { '*' } else { '.' })
+          .collect();
+        let line = line.trim_end_matches('.');
+        text.push_str(if line.is_empty() { "." } else { line });
+        text.push('\n');
+      }
+    }
+    text
+  }
+
+  /// Reads a pattern in the Life 1.06 format: the coordinates of the alive
+  /// cells, one per line, after a `#Life 1.06` header.
+  pub fn from_life_106(text: &str) -> Result<Self, ParsePatternError> {
+    let mut pattern = Pattern::default();
+    for line in text.lines().map(str::trim) {
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+      let coordinates: Vec<i32> = line
+        .split_whitespace()
+        .map(|coordinate| coordinate.parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| ParsePatternError(format!("Invalid Life 1.06 cell '{}'", line)))?;
+      match coordinates[..] {
+        [x, y] => {
+          pattern.cells.insert(Cell { x, y }, 1);
+        }
+        _ => {
+          return Err(ParsePatternError(format!(
+            "Invalid Life 1.06 cell '{}'",
+            line
+          )))
+        }
+      }
+    }
+    Ok(pattern)
+  }
+
+  /// Writes the coordinates of the alive cells of the pattern in the Life
+  /// 1.06 format, row by row.
+  pub fn to_life_106(&self) -> String {
+    let mut cells: Vec<Cell> = self
+      .cells
+      .iter()
+      .filter(|(_, &state)| state == 1)
+      .map(|(&cell, _)| cell)
+      .collect();
+    cells.sort_by_key(|cell| (cell.y, cell.x));
+    let mut text = String::from("#Life 1.06\n");
+    for cell in cells {
+      text.push_str(&format!("{} {}\n", cell.x, cell.y));
+    }
+    text
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::life::CellStates;
+
+  fn glider(dx: i32, dy: i32) -> CellStates {
+    [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]
+      .iter()
+      .map(|&(x, y)| {
+        (
+          Cell {
+            x: x + dx,
+            y: y + dy,
+          },
+          1,
+        )
+      })
+      .collect()
+  }
+
+  #[test]
+  fn reads_and_writes_life_105() {
+    let pattern = Pattern::from_life_105(
+      "#Life 1.05\n#D Two gliders\n#R 23/36\n#P -1 -1\n.*\n..*\n***\n#P 10 20\n.*\n..*\n***\n",
+    )
+    .unwrap();
+    assert_eq!(pattern.comments, vec!["Two gliders".to_string()]);
+    assert_eq!(pattern.rule, Some("B36/S23".to_string()));
+    let mut cells = glider(-1, -1);
+    cells.extend(glider(10, 20));
+    assert_eq!(pattern.cells, cells);
+
+    let mut pattern = Pattern::new(glider(-1, -1));
+    pattern.rule = Some("B3/S23".to_string());
+    let text = pattern.to_life_105();
+    assert_eq!(text, "#Life 1.05\n#N\n#P -1 -1\n.*\n..*\n***\n");
+    assert_eq!(Pattern::from_life_105(&text).unwrap(), pattern);
+    assert!(Pattern::from_life_105("#Life 1.05\n#é\n*\n").is_ok());
+  }
+
+  #[test]
+  fn reads_and_writes_life_106() {
+    let text = "#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n";
+    let pattern = Pattern::from_life_106(text).unwrap();
+    assert_eq!(pattern.cells, glider(-1, -1));
+    assert_eq!(pattern.to_life_106(), text);
+    assert!(Pattern::from_life_106("#Life 1.06\n1 2 3\n").is_err());
+  }
+}
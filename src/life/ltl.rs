@@ -0,0 +1,495 @@
+use crate::life::{alive_cells, decay, CellStates, ParseRuleError, Topology};
+use lexicon::Cell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::RangeInclusive;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Shape {
+  Moore,
+  VonNeumann,
+  /// The cells at a distance of at most `range + 1/2`.
+  Circular,
+}
+
+/// A Larger than Life rule, where the neighborhood extends to `range` cells
+/// in every direction, and only the number of alive cells in it matters.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LargerThanLife {
+  pub range: u32,
+  pub shape: Shape,
+  /// Whether the cell itself is counted in its neighborhood.
+  pub middle: bool,
+  pub birth: Vec<RangeInclusive<u32>>,
+  pub survival: Vec<RangeInclusive<u32>>,
+}
+
+const MAX_RANGE: u32 = 500;
+
+fn parse_number<T: std::str::FromStr>(value: &str, name: &str) -> Result<T, ParseRuleError> {
+  value
+    .trim()
+    .parse()
+    .map_err(|_| ParseRuleError(format!("Invalid {} '{}'", name, value)))
+}
+
+fn parse_range(range: &str) -> Result<RangeInclusive<u32>, ParseRuleError> {
+  let (from, to) = range.split_once("..").unwrap_or((range, range));
+  let (from, to) = (parse_number(from, "count")?, parse_number(to, "count")?);
+  if from > to {
+    return Err(ParseRuleError(format!("Invalid count range '{}'", range)));
+  }
+  Ok(from..=to)
+}
+
+fn format_ranges(ranges: &[RangeInclusive<u32>]) -> String {
+  ranges
+    .iter()
+    .map(|range| format!("{}..{}", range.start(), range.end()))
+    .collect::<Vec<String>>()
+    .join(",")
+}
+
+impl LargerThanLife {
+  /// Parses the Golly/LifeViewer syntax, "R5,C0,M1,S34..58,B34..45,NM",
+  /// returning the rule and its number of states. Several ranges can follow
+  /// S and B, like in "S2..3,5..6".
+  pub fn parse(s: &str) -> Result<(Self, u8), ParseRuleError> {
+    let mut range = None;
+    let mut states = 2;
+    let mut middle = false;
+    let mut shape = Shape::Moore;
+    let mut birth = None;
+    let mut survival = None;
+
+    let mut counts: Option<&mut Vec<RangeInclusive<u32>>> = None;
+    for part in s.split(',').map(str::trim) {
+      let (letter, value) = part.split_at(part.chars().next().map_or(0, char::len_utf8));
+      match letter.to_ascii_uppercase().as_str() {
+        "R" => match parse_number(value, "range")? {
+          r @ 1..=MAX_RANGE => range = Some(r),
+          _ => {
+            return Err(ParseRuleError(format!(
+              "The range must be between 1 and {}",
+              MAX_RANGE
+            )))
+          }
+        },
+        "C" => match parse_number(value, "number of states")? {
+          0 | 1 => states = 2,
+          c => states = c,
+        },
+        "M" => middle = parse_number::<u8>(value, "middle")? == 1,
+        "N" => {
+          shape = match value.to_ascii_uppercase().as_str() {
+            "M" => Shape::Moore,
+            "N" => Shape::VonNeumann,
+            "C" => Shape::Circular,
+            _ => {
+              return Err(ParseRuleError(format!(
+                "Unknown neighborhood '{}', expected NM, NN or NC",
+                part
+              )))
+            }
+          }
+        }
+        "S" => counts = Some(survival.insert(vec![parse_range(value)?])),
+        "B" => counts = Some(birth.insert(vec![parse_range(value)?])),
+        _ if part.starts_with(|c: char| c.is_ascii_digit()) => match counts.as_mut() {
+          Some(counts) => counts.push(parse_range(part)?),
+          None => return Err(ParseRuleError(format!("Unexpected range '{}'", part))),
+        },
+        _ => return Err(ParseRuleError(format!("Unexpected '{}'", part))),
+      }
+    }
+
+    match (range, birth, survival) {
+      (_, Some(birth), _) if birth.iter().any(|range| range.contains(&0)) => Err(ParseRuleError(
+        "Rules with B0 are not supported".to_string(),
+      )),
+      (Some(range), Some(birth), Some(survival)) => Ok((
+        LargerThanLife {
+          range,
+          shape,
+          middle,
+          birth,
+          survival,
+        },
+        states,
+      )),
+      _ => Err(ParseRuleError(
+        "A Larger than Life rule needs R, S and B parts".to_string(),
+      )),
+    }
+  }
+
+  pub fn next_state(&self, alive: bool, count: u32) -> bool {
+    let ranges = if alive { &self.survival } else { &self.birth };
+    ranges.iter().any(|range| range.contains(&count))
+  }
+
+  /// How far the neighborhood extends on each side, on the row `dy` rows
+  /// away from the cell.
+  fn half_width(&self, dy: i32) -> i32 {
+    let range = self.range as i32;
+    match self.shape {
+      Shape::Moore => range,
+      Shape::VonNeumann => range - dy.abs(),
+      Shape::Circular => ((range * range + range - dy * dy) as f64).sqrt() as i32,
+    }
+  }
+
+  /// Writes the rule in the Golly syntax, given its number of states.
+  pub fn format(&self, f: &mut fmt::Formatter, states: u8) -> fmt::Result {
+    write!(
+      f,
+      "R{},C{},M{},S{},B{},N{}",
+      self.range,
+      if states > 2 { states } else { 0 },
+      self.middle as u8,
+      format_ranges(&self.survival),
+      format_ranges(&self.birth),
+      match self.shape {
+        Shape::Moore => "M",
+        Shape::VonNeumann => "N",
+        Shape::Circular => "C",
+      }
+    )
+  }
+}
+
+/// The smallest side of the tiles the universe is cut into. Each tile is
+/// computed on its own, so that objects far apart don't need a grid covering
+/// all the space between them.
+const MIN_TILE_SIZE: i64 = 64;
+
+/// The tile at the given position and the eight ones around it.
+fn around((x, y): (i64, i64)) -> impl Iterator<Item = (i64, i64)> {
+  (y - 1..=y + 1).flat_map(move |y| (x - 1..=x + 1).map(move |x| (x, y)))
+}
+
+fn to_cell(x: i64, y: i64) -> Option<Cell> {
+  Some(Cell {
+    x: x.try_into().ok()?,
+    y: y.try_into().ok()?,
+  })
+}
+
+/// Computes the next generation of a Larger than Life rule. The universe is
+/// cut into tiles at least twice as large as the range, and the cells of
+/// each tile near alive ones are laid out in a grid with a margin of `range`
+/// cells. Each row of the grid is turned into running sums, so that
+/// counting the alive cells in a row of the neighborhood only takes a
+/// subtraction.
+pub fn tick(
+  cells: &CellStates,
+  rule: &LargerThanLife,
+  states: u8,
+  topology: &Topology,
+) -> CellStates {
+  let mut next_cells = decay(cells, states);
+  let alive_cells = alive_cells(cells);
+  if alive_cells.is_empty() {
+    return next_cells;
+  }
+
+  let range = rule.range as i64;
+  let size = (2 * range).max(MIN_TILE_SIZE);
+  let tile_of = |x: i64, y: i64| (x.div_euclid(size), y.div_euclid(size));
+  let inside = |x: i64, y: i64| match topology.bounds() {
+    None => true,
+    Some((top_left, width, height)) => {
+      let (left, top) = (top_left.x as i64, top_left.y as i64);
+      (left..left + width as i64).contains(&x) && (top..top + height as i64).contains(&y)
+    }
+  };
+  let mut tiles: HashMap<(i64, i64), Vec<Cell>> = HashMap::new();
+  for &cell in &alive_cells {
+    tiles
+      .entry(tile_of(cell.x as i64, cell.y as i64))
+      .or_default()
+      .push(cell);
+  }
+
+  // The tiles that can hold alive cells in the next generation, following
+  // the edges of bounded universes from the tiles sticking out of them.
+  let mut targets = HashSet::new();
+  for &tile in tiles.keys() {
+    for (tx, ty) in around(tile) {
+      let (left, top) = (tx * size, ty * size);
+      if inside(left, top) && inside(left + size - 1, top + size - 1) {
+        targets.insert((tx, ty));
+        continue;
+      }
+      for y in top..top + size {
+        for x in left..left + size {
+          let cell = to_cell(x, y).and_then(|cell| topology.normalize(cell));
+          if let Some(cell) = cell {
+            targets.insert(tile_of(cell.x as i64, cell.y as i64));
+          }
+        }
+      }
+    }
+  }
+
+  let side = size + 2 * range;
+  let half_widths: Vec<i64> = (-range..=range)
+    .map(|dy| rule.half_width(dy as i32) as i64)
+    .collect();
+  for (tx, ty) in targets {
+    let (left, top) = (tx * size, ty * size);
+    let index = |x: i64, y: i64| ((y - top + range) * side + (x - left + range)) as usize;
+    let within = |x: i64, y: i64| {
+      (left - range..left + size + range).contains(&x)
+        && (top - range..top + size + range).contains(&y)
+    };
+
+    // The bounding box of the alive cells in the grid, outside of which
+    // nothing can be born.
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (i64::MAX, i64::MAX, i64::MIN, i64::MIN);
+    let mut grid = vec![false; (side * side) as usize];
+    let mut set = |x: i64, y: i64| {
+      grid[index(x, y)] = true;
+      (min_x, min_y) = (min_x.min(x), min_y.min(y));
+      (max_x, max_y) = (max_x.max(x), max_y.max(y));
+    };
+    for cell in around((tx, ty)).flat_map(|tile| tiles.get(&tile).into_iter().flatten()) {
+      let (x, y) = (cell.x as i64, cell.y as i64);
+      if within(x, y) {
+        set(x, y);
+      }
+    }
+    if !inside(left - range, top - range)
+      || !inside(left + size + range - 1, top + size + range - 1)
+    {
+      for y in top - range..top + size + range {
+        for x in left - range..left + size + range {
+          if inside(x, y) {
+            continue;
+          }
+          let cell = to_cell(x, y).and_then(|cell| topology.normalize(cell));
+          if cell.is_some_and(|cell| alive_cells.contains(&cell)) {
+            set(x, y);
+          }
+        }
+      }
+    }
+    if min_x > max_x {
+      continue;
+    }
+
+    let sums_width = side + 1;
+    let mut sums = vec![0_u32; (sums_width * side) as usize];
+    for y in 0..side {
+      for x in 0..side {
+        let i = (y * sums_width + x) as usize;
+        sums[i + 1] = sums[i] + grid[(y * side + x) as usize] as u32;
+      }
+    }
+
+    for y in (top.max(min_y - range))..(top + size).min(max_y + range + 1) {
+      for x in (left.max(min_x - range))..(left + size).min(max_x + range + 1) {
+        let cell = match to_cell(x, y) {
+          Some(cell) if inside(x, y) => cell,
+          _ => continue,
+        };
+        if cells.get(&cell).is_some_and(|&state| state > 1) {
+          continue;
+        }
+        let alive = grid[index(x, y)];
+        let (gx, gy) = (x - left + range, y - top + range);
+        let mut count: u32 = half_widths
+          .iter()
+          .zip(gy - range..=gy + range)
+          .map(|(&half_width, gy)| {
+            let row = gy * sums_width;
+            sums[(row + gx + half_width + 1) as usize] - sums[(row + gx - half_width) as usize]
+          })
+          .sum();
+        if alive && !rule.middle {
+          count -= 1;
+        }
+        if count == 0 && !alive {
+          continue;
+        }
+        if rule.next_state(alive, count) {
+          next_cells.insert(cell, 1);
+        } else if alive && states > 2 {
+          next_cells.insert(cell, 2);
+        }
+      }
+    }
+  }
+
+  next_cells
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::life::{from_alive_cells, tick as tick_moore, CellSet, Rule};
+
+  fn soup() -> CellSet {
+    let mut seed = 7_u32;
+    let mut cells = CellSet::new();
+    for x in -12..12 {
+      for y in -12..12 {
+        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+        if seed >> 16 & 1 != 0 {
+          cells.insert(Cell { x, y });
+        }
+      }
+    }
+    cells
+  }
+
+  fn naive_tick(cells: &CellSet, rule: &LargerThanLife) -> CellSet {
+    let range = rule.range as i32;
+    let candidates: CellSet = cells
+      .iter()
+      .flat_map(|cell| {
+        (-range..=range).flat_map(move |dy| {
+          (-range..=range).map(move |dx| Cell {
+            x: cell.x + dx,
+            y: cell.y + dy,
+          })
+        })
+      })
+      .collect();
+    candidates
+      .into_iter()
+      .filter(|&cell| {
+        let count = (-range..=range)
+          .flat_map(|dy| {
+            let half_width = rule.half_width(dy);
+            (-half_width..=half_width).map(move |dx| (dx, dy))
+          })
+          .filter(|&(dx, dy)| rule.middle || (dx, dy) != (0, 0))
+          .filter(|&(dx, dy)| {
+            cells.contains(&Cell {
+              x: cell.x + dx,
+              y: cell.y + dy,
+            })
+          })
+          .count();
+        rule.next_state(cells.contains(&cell), count as u32)
+      })
+      .collect()
+  }
+
+  #[test]
+  fn parses_and_formats() {
+    let (rule, states) = LargerThanLife::parse("R5,C0,M1,S34..58,B34..45,NM").unwrap();
+    assert_eq!(states, 2);
+    assert_eq!(rule.range, 5);
+    assert!(rule.middle);
+    assert_eq!(rule.survival, vec![34..=58]);
+    assert_eq!(rule.birth, vec![34..=45]);
+
+    let (rule, states) = LargerThanLife::parse("r2,c3,m0,s2..3,5..6,b4,nc").unwrap();
+    assert_eq!(states, 3);
+    assert_eq!(rule.shape, Shape::Circular);
+    assert_eq!(rule.survival, vec![2..=3, 5..=6]);
+    assert_eq!(rule.birth, vec![4..=4]);
+  }
+
+  #[test]
+  fn rejects_invalid_rules() {
+    assert!(LargerThanLife::parse("R5,C0,M1,S34..58").is_err());
+    assert!(LargerThanLife::parse("R0,C0,M1,S34..58,B34..45,NM").is_err());
+    assert!(LargerThanLife::parse("R5,C0,M1,S34..58,B34..45,NX").is_err());
+    assert!(LargerThanLife::parse("R5,C0,M1,S34..58,B0..45,NM").is_err());
+  }
+
+  #[test]
+  fn range_1_moore_is_conway() {
+    let (rule, states) = LargerThanLife::parse("R1,C0,M0,S2..3,B3,NM").unwrap();
+    let mut cells = soup();
+    for _ in 0..10 {
+      let expected = tick_moore(&cells, &Rule::conway());
+      cells = alive_cells(&tick(
+        &from_alive_cells(&cells),
+        &rule,
+        states,
+        &Topology::Infinite,
+      ));
+      assert_eq!(cells, expected);
+    }
+  }
+
+  #[test]
+  fn running_sums_match_naive_counting() {
+    for notation in [
+      "R3,C0,M1,S10..20,B8..14,NM",
+      "R3,C0,M0,S5..10,B6..8,NN",
+      "R4,C0,M1,S12..25,B13..20,NC",
+    ] {
+      let (rule, states) = LargerThanLife::parse(notation).unwrap();
+      let mut cells = soup();
+      for _ in 0..5 {
+        let expected = naive_tick(&cells, &rule);
+        cells = alive_cells(&tick(
+          &from_alive_cells(&cells),
+          &rule,
+          states,
+          &Topology::Infinite,
+        ));
+        assert_eq!(cells, expected, "{}", notation);
+      }
+    }
+  }
+
+  #[test]
+  fn computes_distant_objects_separately() {
+    let (rule, states) = LargerThanLife::parse("R2,C0,M1,S5..9,B6..7,NM").unwrap();
+    let mut cells: CellSet = soup()
+      .into_iter()
+      .flat_map(|cell| {
+        [
+          cell,
+          Cell {
+            x: cell.x + (1 << 30),
+            y: cell.y - (1 << 30),
+          },
+        ]
+      })
+      .collect();
+    for _ in 0..5 {
+      let expected = naive_tick(&cells, &rule);
+      cells = alive_cells(&tick(
+        &from_alive_cells(&cells),
+        &rule,
+        states,
+        &Topology::Infinite,
+      ));
+      assert_eq!(cells, expected);
+    }
+  }
+
+  #[test]
+  fn follows_the_edges_of_huge_universes() {
+    let (rule, states) = LargerThanLife::parse("R1,C0,M0,S2..3,B3,NM").unwrap();
+    for notation in ["B3/S23:T30,20", "B3/S23:K1000000*,1000000"] {
+      let conway: Rule = notation.parse().unwrap();
+      let (top_left, _, _) = conway.topology.bounds().unwrap();
+      let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+      let mut cells = glider
+        .iter()
+        .map(|&(x, y)| {
+          (
+            Cell {
+              x: top_left.x - x + 2,
+              y: top_left.y - y + 2,
+            },
+            1,
+          )
+        })
+        .collect();
+      for _ in 0..20 {
+        let expected = crate::life::step(&cells, &conway);
+        cells = tick(&cells, &rule, states, &conway.topology);
+        assert_eq!(cells, expected, "{}", notation);
+      }
+    }
+  }
+}
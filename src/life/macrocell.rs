@@ -0,0 +1,349 @@
+use crate::life::hashlife::{HashLife, NodeId, ALIVE, DEAD};
+use crate::life::{alive_cells, LifeEngine, ParsePatternError, Pattern, Rule};
+use std::collections::HashMap;
+
+/// The level of the nodes written as 8x8 squares of cells.
+const LEAF_LEVEL: u8 = 3;
+
+/// Past this level, the coordinates of the cells wouldn’t fit in an `i32`
+/// with the root centered on the origin.
+const MAX_LEVEL: u8 = 31;
+
+/// A pattern read from a macrocell file, kept as a quadtree so that it
+/// doesn’t have to fit in memory once expanded.
+pub struct Macrocell {
+  pub comments: Vec<String>,
+  /// The rule it runs in, as written in the file.
+  pub rule: Option<String>,
+  pub universe: HashLife,
+}
+
+impl Macrocell {
+  /// Reads a pattern in Golly’s macrocell format: a `[M2]` header, `#R` rule
+  /// and `#C` comment lines, then the nodes of the quadtree, one per line.
+  /// Nodes of 8x8 cells are written as rows of `.` and `*` ending with `$`,
+  /// the other ones as their level followed by the line numbers of their
+  /// four quadrants, 0 being empty. The last node is the root, centered on
+  /// the origin.
+  pub fn parse(text: &str) -> Result<Self, ParsePatternError> {
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    if !lines.next().is_some_and(|line| line.starts_with("[M2]")) {
+      return Err(ParsePatternError(
+        "Missing the [M2] macrocell header".to_string(),
+      ));
+    }
+    let mut comments = vec![];
+    let mut rule = None;
+    let mut universe = HashLife::new(&Rule::conway());
+    // Line numbers start at 1, 0 standing for empty nodes
+    let mut nodes = vec![DEAD];
+    for line in lines {
+      if let Some(comment) = line.strip_prefix('#') {
+        let (tag, value) = comment.split_at(comment.chars().next().map_or(0, char::len_utf8));
+        let value = value.trim().to_string();
+        match tag {
+          "R" => rule = Some(value),
+          "C" | "D" | "N" | "O" => comments.push(value),
+          _ => {}
+        }
+      } else if line.starts_with(|c: char| c.is_ascii_digit()) {
+        let node = universe.parse_node(line, &nodes)?;
+        nodes.push(node);
+      } else {
+        let leaf = universe.parse_leaf(line)?;
+        nodes.push(leaf);
+      }
+    }
+    if nodes.len() > 1 {
+      universe.set_root(nodes[nodes.len() - 1]);
+    }
+    if let Some(Ok(rule)) = rule.as_deref().map(str::parse::<Rule>) {
+      if rule.is_life_like() {
+        universe.rule = rule;
+      }
+    }
+    Ok(Self {
+      comments,
+      rule,
+      universe,
+    })
+  }
+}
+
+impl HashLife {
+  /// Writes the universe in Golly’s macrocell format, each node only once
+  /// however many times it appears.
+  pub fn to_macrocell(&self, comments: &[String]) -> String {
+    let mut text = String::from("[M2] (yew-app)\n");
+    text.push_str(&format!("#R {}\n", self.rule));
+    for comment in comments {
+      text.push_str(&format!("#C {}\n", comment));
+    }
+    let mut numbers = HashMap::new();
+    let mut lines = vec![];
+    self.write_node(self.root, &mut numbers, &mut lines);
+    for line in lines {
+      text.push_str(&line);
+      text.push('\n');
+    }
+    text
+  }
+
+  /// Replaces the universe with a node, centered on the origin.
+  fn set_root(&mut self, root: NodeId) {
+    let half = 1_i64 << (self.node(root).level - 1);
+    self.root = root;
+    self.origin = (-half, -half);
+    while self.node(self.root).level < LEAF_LEVEL {
+      self.expand();
+    }
+  }
+
+  fn parse_leaf(&mut self, line: &str) -> Result<NodeId, ParsePatternError> {
+    let mut grid = [[false; 8]; 8];
+    let (mut x, mut y) = (0, 0);
+    for c in line.chars() {
+      match c {
+        '.' | '*' if x >= 8 || y >= 8 => {
+          return Err(ParsePatternError(format!(
+            "Macrocell leaf larger than 8x8 '{}'",
+            line
+          )))
+        }
+        '.' => x += 1,
+        '*' => {
+          grid[y][x] = true;
+          x += 1;
+        }
+        '$' => {
+          x = 0;
+          y += 1;
+        }
+        _ => {
+          return Err(ParsePatternError(format!(
+            "Unexpected '{}' in macrocell",
+            c
+          )))
+        }
+      }
+    }
+    Ok(self.grid_node(&grid, (0, 0), LEAF_LEVEL))
+  }
+
+  /// The node of a square of the grid.
+  fn grid_node(&mut self, grid: &[[bool; 8]; 8], (x, y): (usize, usize), level: u8) -> NodeId {
+    if level == 0 {
+      return if grid[y][x] { ALIVE } else { DEAD };
+    }
+    let half = 1 << (level - 1);
+    let nw = self.grid_node(grid, (x, y), level - 1);
+    let ne = self.grid_node(grid, (x + half, y), level - 1);
+    let sw = self.grid_node(grid, (x, y + half), level - 1);
+    let se = self.grid_node(grid, (x + half, y + half), level - 1);
+    self.join(nw, ne, sw, se)
+  }
+
+  /// Reads a `level nw ne sw se` line, the quadrants being the line numbers
+  /// of earlier nodes, or cells for level 1 nodes.
+  fn parse_node(&mut self, line: &str, nodes: &[NodeId]) -> Result<NodeId, ParsePatternError> {
+    let invalid = || ParsePatternError(format!("Invalid macrocell node '{}'", line));
+    let numbers: Vec<usize> = line
+      .split_whitespace()
+      .map(|number| number.parse())
+      .collect::<Result<_, _>>()
+      .map_err(|_| invalid())?;
+    let (level, quadrants) = match numbers[..] {
+      [level, ..] if level > MAX_LEVEL as usize => {
+        return Err(ParsePatternError(format!(
+          "Macrocell patterns can’t be larger than level {}",
+          MAX_LEVEL
+        )))
+      }
+      [level, nw, ne, sw, se] if level > 0 => (level as u8, [nw, ne, sw, se]),
+      _ => return Err(invalid()),
+    };
+    let mut children = [DEAD; 4];
+    for (child, &number) in children.iter_mut().zip(quadrants.iter()) {
+      *child = match (level, number) {
+        (1, 0) => DEAD,
+        (1, 1) => ALIVE,
+        (1, _) => return Err(invalid()),
+        (_, 0) => self.empty_node(level - 1),
+        (_, number) => {
+          let node = *nodes.get(number).ok_or_else(invalid)?;
+          if self.node(node).level != level - 1 {
+            return Err(invalid());
+          }
+          node
+        }
+      };
+    }
+    let [nw, ne, sw, se] = children;
+    Ok(self.join(nw, ne, sw, se))
+  }
+
+  /// Adds the lines of a node and the ones it is made of, if they aren’t
+  /// there yet, and returns its line number.
+  fn write_node(
+    &self,
+    id: NodeId,
+    numbers: &mut HashMap<NodeId, usize>,
+    lines: &mut Vec<String>,
+  ) -> usize {
+    let node = self.node(id);
+    if node.population == 0 {
+      return 0;
+    }
+    if let Some(&number) = numbers.get(&id) {
+      return number;
+    }
+    let line = if node.level == LEAF_LEVEL {
+      let mut grid = [[false; 8]; 8];
+      self.fill_grid(id, (0, 0), &mut grid);
+      let rows: Vec<String> = grid
+        .iter()
+        .map(|row| {
+          let row: String = row
+            .iter()
+            .map(|&alive| if alive { '*' } else { '.' })
+            .collect();
+          format!("{}$", row.trim_end_matches('.'))
+        })
+        .collect();
+      let last = grid.iter().rposition(|row| row.contains(&true)).unwrap();
+      rows[..=last].concat()
+    } else {
+      let nw = self.write_node(node.nw, numbers, lines);
+      let ne = self.write_node(node.ne, numbers, lines);
+      let sw = self.write_node(node.sw, numbers, lines);
+      let se = self.write_node(node.se, numbers, lines);
+      format!("{} {} {} {} {}", node.level, nw, ne, sw, se)
+    };
+    lines.push(line);
+    numbers.insert(id, lines.len());
+    lines.len()
+  }
+
+  fn fill_grid(&self, id: NodeId, (x, y): (usize, usize), grid: &mut [[bool; 8]; 8]) {
+    let node = self.node(id);
+    if node.level == 0 {
+      grid[y][x] = id == ALIVE;
+      return;
+    }
+    let half = 1 << (node.level - 1);
+    self.fill_grid(node.nw, (x, y), grid);
+    self.fill_grid(node.ne, (x + half, y), grid);
+    self.fill_grid(node.sw, (x, y + half), grid);
+    self.fill_grid(node.se, (x + half, y + half), grid);
+  }
+}
+
+impl Pattern {
+  /// Reads a pattern in the macrocell format, expanding it into cells. Only
+  /// use this on patterns that fit in memory once expanded.
+  pub fn from_macrocell(text: &str) -> Result<Self, ParsePatternError> {
+    let macrocell = Macrocell::parse(text)?;
+    Ok(Pattern {
+      comments: macrocell.comments,
+      rule: macrocell.rule,
+      cells: macrocell.universe.cells(),
+      ..Pattern::default()
+    })
+  }
+
+  /// Writes the alive cells of the pattern in the macrocell format. The name
+  /// and author go in the comments.
+  pub fn to_macrocell(&self) -> String {
+    let rule = match self.rule.as_deref().map(str::parse::<Rule>) {
+      Some(Ok(rule)) if rule.is_life_like() => rule,
+      _ => Rule::conway(),
+    };
+    let comments: Vec<String> = self
+      .name
+      .iter()
+      .chain(self.author.iter())
+      .chain(self.comments.iter())
+      .cloned()
+      .collect();
+    HashLife::from_cells(&alive_cells(&self.cells), &rule).to_macrocell(&comments)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use lexicon::Cell;
+
+  #[test]
+  fn reads_and_writes_macrocell() {
+    let text = "[M2] (golly 4.2)\n#R B36/S23\n#C A glider\n.*$..*$***$\n4 0 0 0 1\n";
+    let macrocell = Macrocell::parse(text).unwrap();
+    assert_eq!(macrocell.rule, Some("B36/S23".to_string()));
+    assert_eq!(macrocell.comments, vec!["A glider".to_string()]);
+    let mut cells: Vec<(i32, i32)> = macrocell
+      .universe
+      .cells()
+      .keys()
+      .map(|cell| (cell.x, cell.y))
+      .collect();
+    cells.sort_by_key(|&(x, y)| (y, x));
+    assert_eq!(cells, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+    assert_eq!(
+      macrocell.universe.to_macrocell(&macrocell.comments),
+      "[M2] (yew-app)\n#R B36/S23\n#C A glider\n.*$..*$***$\n4 0 0 0 1\n"
+    );
+    assert!(Macrocell::parse("[M2]\n.*$\n4 0 0 0 2\n").is_err());
+    assert!(Macrocell::parse(".*$\n").is_err());
+    assert!(Macrocell::parse("[M2]\n#é\n.*$\n").is_ok());
+  }
+
+  #[test]
+  fn keeps_huge_patterns_compressed() {
+    let mut text = String::from("[M2] (yew-app)\n#R B3/S23\n.*$..*$***$\n");
+    for level in 4..=20 {
+      let number = level - 3;
+      text.push_str(&format!(
+        "{} {} {} {} {}\n",
+        level, number, number, number, number
+      ));
+    }
+    let macrocell = Macrocell::parse(&text).unwrap();
+    assert_eq!(macrocell.universe.population(), 5 << 34);
+    assert_eq!(
+      macrocell.universe.bounding_box(),
+      Some((
+        Cell {
+          x: -524288,
+          y: -524288
+        },
+        Cell {
+          x: 524282,
+          y: 524282
+        }
+      ))
+    );
+    assert_eq!(macrocell.universe.to_macrocell(&[]), text);
+
+    // Coordinates wouldn’t fit past level 31
+    for level in 21..=32 {
+      let number = level - 3;
+      text.push_str(&format!(
+        "{} {} {} {} {}\n",
+        level, number, number, number, number
+      ));
+    }
+    assert!(Macrocell::parse(&text).is_err());
+  }
+
+  #[test]
+  fn round_trips_patterns() {
+    let cells = [(-20, 3), (0, 0), (1, 0), (7, 8), (100, -50)]
+      .iter()
+      .map(|&(x, y)| (Cell { x, y }, 1))
+      .collect();
+    let mut pattern = Pattern::new(cells);
+    pattern.rule = Some("B3/S23".to_string());
+    let text = pattern.to_macrocell();
+    assert_eq!(Pattern::from_macrocell(&text).unwrap(), pattern);
+  }
+}
@@ -1,8 +1,77 @@
+mod activity;
+mod apgcode;
+mod census;
+mod collision;
+pub mod engine;
+pub mod hashlife;
+mod hensel;
+mod history;
+mod identify;
+mod lif;
+mod ltl;
+mod macrocell;
+mod neighborhood;
+mod pattern;
+mod period;
+mod plaintext;
+mod predecessor;
+mod rle;
+mod rule;
+mod sat;
+mod snapshot;
+mod soup;
+pub mod sparse;
+mod stabilisation;
+mod table;
+pub mod tiled;
+mod topology;
+
+pub use activity::Activity;
+pub use apgcode::{
+  apgcode_cells, canonical_wechsler, common_name, from_wechsler, orientations, wechsler,
+};
+pub use census::{apgcode_of, census, tally, Object};
+pub use collision::{collide, Incoming, Outcome, Setup, MAX_LANE, MAX_TIMING};
+pub use engine::{Area, Engine, LifeEngine};
+pub use hensel::Neighborhoods;
+pub use history::{History, Record};
+pub use identify::{canonical_form, LexiconIndex};
+pub use ltl::{LargerThanLife, Shape};
+pub use macrocell::Macrocell;
+pub use neighborhood::Neighborhood;
+pub use pattern::{Format, ParsePatternError, Pattern};
+pub use period::{PeriodDetector, Periodicity};
+pub use predecessor::{predecessor, Predecessor};
+pub use rule::{ParseRuleError, Rule, Transitions};
+pub use snapshot::{Snapshot, MAX_SHARED_CELLS};
+pub use soup::{soup, Symmetry, SYMMETRIES};
+pub use stabilisation::{Stabilisation, StabilisationDetector};
+pub use table::{Rgb, RuleTable};
+pub use topology::Topology;
+
 use lexicon::Cell;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 pub type CellSet = HashSet<Cell>;
 
+/// The state of every cell that isn't dead (0). Alive cells are in state 1,
+/// cells dying under a Generations rule in the next ones, while rule tables
+/// give any meaning to any state.
+pub type CellStates = HashMap<Cell, u8>;
+
+/// The cells in state 1, as used by the two-state engines.
+pub fn alive_cells(cells: &CellStates) -> CellSet {
+  cells
+    .iter()
+    .filter(|(_, &state)| state == 1)
+    .map(|(&cell, _)| cell)
+    .collect()
+}
+
+pub fn from_alive_cells(cells: &CellSet) -> CellStates {
+  cells.iter().map(|&cell| (cell, 1)).collect()
+}
+
 fn singleton(cell: Cell) -> CellSet {
   let mut cells = CellSet::new();
   cells.insert(cell);
@@ -17,58 +86,149 @@ pub fn make_cell_dead(cells: &CellSet, cell: Cell) -> CellSet {
   cells.difference(&singleton(cell)).copied().collect()
 }
 
-pub fn tick(cells: &CellSet) -> CellSet {
-  cells_with_neighbors(cells)
+pub fn tick(cells: &CellSet, rule: &Rule) -> CellSet {
+  cells_with_neighbors(cells, rule)
     .iter()
     .filter(|&&cell| {
-      let alive_neighbors = number_of_alive_neighbors(cells, cell);
-      if cell_is_alive(cells, cell) {
-        alive_neighbors == 2 || alive_neighbors == 3
-      } else {
-        alive_neighbors == 3
-      }
+      let neighborhood = alive_neighborhood(cells, cell, &rule.topology);
+      rule.next_state(cell_is_alive(cells, cell), neighborhood)
     })
     .map(|&c| c)
     .collect()
 }
 
+/// The cells in the dying states of a Generations rule, one state further.
+fn decay(cells: &CellStates, states: u8) -> CellStates {
+  cells
+    .iter()
+    .filter(|(_, &state)| state > 1 && state + 1 < states)
+    .map(|(&cell, &state)| (cell, state + 1))
+    .collect()
+}
+
+pub fn tick_generations(cells: &CellStates, rule: &Rule) -> CellStates {
+  let alive = alive_cells(cells);
+  let mut next_cells = decay(cells, rule.states);
+
+  for cell in cells_with_neighbors(&alive, rule) {
+    if cells.get(&cell).is_some_and(|&state| state > 1) {
+      continue;
+    }
+    let is_alive = cell_is_alive(&alive, cell);
+    let neighborhood = alive_neighborhood(&alive, cell, &rule.topology);
+    if rule.next_state(is_alive, neighborhood) {
+      next_cells.insert(cell, 1);
+    } else if is_alive && rule.states > 2 {
+      next_cells.insert(cell, 2);
+    }
+  }
+
+  next_cells
+}
+
+/// The next generation under any rule.
+pub fn step(cells: &CellStates, rule: &Rule) -> CellStates {
+  match &rule.transitions {
+    Transitions::LifeLike { .. } => tick_generations(cells, rule),
+    Transitions::Extended(extended) => ltl::tick(cells, extended, rule.states, &rule.topology),
+    Transitions::Table(table) => table::tick(cells, table, &rule.topology),
+  }
+}
+
 pub fn cell_is_alive(cells: &CellSet, cell: Cell) -> bool {
   cells.contains(&cell)
 }
 
-fn cells_with_neighbors(cells: &HashSet<Cell>) -> CellSet {
+fn cells_with_neighbors(cells: &HashSet<Cell>, rule: &Rule) -> CellSet {
+  let neighborhood = rule.neighborhood();
   cells
     .iter()
     .flat_map(|&cell| {
-      let mut neighbors = cell_neighbors(cell);
+      let mut neighbors = neighborhood.neighbors(cell, &rule.topology);
       neighbors.push(cell);
       neighbors
     })
     .collect()
 }
 
-fn number_of_alive_neighbors(cells: &CellSet, cell: Cell) -> usize {
-  cell_neighbors(cell)
+/// Offsets of the eight surrounding cells, clockwise from the north one,
+/// which is also the order of the bits of a neighborhood.
+pub(crate) const NEIGHBORS: [(i32, i32); 8] = [
+  (0, -1),
+  (1, -1),
+  (1, 0),
+  (1, 1),
+  (0, 1),
+  (-1, 1),
+  (-1, 0),
+  (-1, -1),
+];
+
+fn alive_neighborhood(cells: &CellSet, cell: Cell, topology: &Topology) -> u8 {
+  NEIGHBORS
     .iter()
-    .map(|&neighbor| cell_is_alive(cells, neighbor))
-    .filter(|&b| b)
-    .count()
+    .enumerate()
+    .filter(|(_, &(dx, dy))| {
+      topology
+        .normalize(Cell {
+          x: cell.x + dx,
+          y: cell.y + dy,
+        })
+        .is_some_and(|neighbor| cell_is_alive(cells, neighbor))
+    })
+    .fold(0, |neighborhood, (i, _)| neighborhood | 1 << i)
 }
 
-fn cell_neighbors(cell: Cell) -> Vec<Cell> {
-  (-1..=1)
-    .into_iter()
-    .flat_map(|dx| {
-      (-1..=1).into_iter().flat_map(move |dy| {
-        let mut set = vec![];
-        if dx != 0 || dy != 0 {
-          set.push(Cell {
-            x: cell.x + dx,
-            y: cell.y + dy,
-          });
-        }
-        set
-      })
-    })
-    .collect()
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn cells(coordinates: &[(i32, i32)]) -> CellSet {
+    coordinates.iter().map(|&(x, y)| Cell { x, y }).collect()
+  }
+
+  #[test]
+  fn tick_runs_a_blinker() {
+    let rule = Rule::conway();
+    let blinker = cells(&[(0, 1), (1, 1), (2, 1)]);
+    assert_eq!(tick(&blinker, &rule), cells(&[(1, 0), (1, 1), (1, 2)]));
+  }
+
+  #[test]
+  fn tick_wraps_around_a_torus() {
+    let rule: Rule = "B3/S23:T4,4".parse().unwrap();
+    let blinker = cells(&[(-2, 1), (-1, 1), (0, 1)]);
+    assert_eq!(tick(&blinker, &rule), cells(&[(-1, -2), (-1, 0), (-1, 1)]));
+  }
+
+  #[test]
+  fn tick_generations_decays_dying_cells() {
+    let rule: Rule = "/2/3".parse().unwrap();
+    let next = tick_generations(&from_alive_cells(&cells(&[(0, 0), (1, 0)])), &rule);
+    assert_eq!(
+      alive_cells(&next),
+      cells(&[(0, -1), (1, -1), (0, 1), (1, 1)])
+    );
+    assert_eq!(next.len(), 6);
+    assert_eq!(next.get(&Cell { x: 0, y: 0 }), Some(&2));
+
+    let next = tick_generations(&next, &rule);
+    assert!(!next.contains_key(&Cell { x: 0, y: 0 }));
+  }
+
+  #[test]
+  fn tick_ignores_cells_outside_the_neighborhood() {
+    let rule: Rule = "B2/S34V".parse().unwrap();
+    let diagonal = cells(&[(0, 0), (1, 1)]);
+    assert_eq!(tick(&diagonal, &rule), cells(&[(1, 0), (0, 1)]));
+
+    let rule: Rule = "B2/S34H".parse().unwrap();
+    assert_eq!(tick(&diagonal, &rule), cells(&[(1, 0), (0, 1)]));
+    let anti_diagonal = cells(&[(1, -1), (-1, 1)]);
+    assert_eq!(tick(&anti_diagonal, &rule), CellSet::new());
+    assert_eq!(
+      tick(&anti_diagonal, &"B2/S".parse().unwrap()),
+      cells(&[(0, 0)])
+    );
+  }
 }
@@ -0,0 +1,77 @@
+use crate::life::{Topology, NEIGHBORS};
+use lexicon::Cell;
+
+/// Which of the eight surrounding cells count as neighbors. Hexagonal grids
+/// are laid out like Golly does, as a square grid where the north-east and
+/// south-west cells are not neighbors, each row being shifted half a cell to
+/// the left of the one above.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Neighborhood {
+  #[default]
+  Moore,
+  Hexagonal,
+  VonNeumann,
+}
+
+impl Neighborhood {
+  /// The neighbors as a bit mask, in the order of `NEIGHBORS`.
+  pub fn mask(&self) -> u8 {
+    match self {
+      Neighborhood::Moore => 0b1111_1111,
+      Neighborhood::Hexagonal => 0b1101_1101,
+      Neighborhood::VonNeumann => 0b0101_0101,
+    }
+  }
+
+  pub fn size(&self) -> usize {
+    self.mask().count_ones() as usize
+  }
+
+  /// The letter ending rules in this neighborhood, like "B2/S34H".
+  pub fn suffix(&self) -> &'static str {
+    match self {
+      Neighborhood::Moore => "",
+      Neighborhood::Hexagonal => "H",
+      Neighborhood::VonNeumann => "V",
+    }
+  }
+
+  pub fn offsets(&self) -> impl Iterator<Item = (i32, i32)> {
+    let mask = self.mask();
+    NEIGHBORS
+      .into_iter()
+      .enumerate()
+      .filter(move |(i, _)| mask & 1 << i != 0)
+      .map(|(_, offset)| offset)
+  }
+
+  /// The neighbors of a cell in the universe.
+  pub fn neighbors(&self, cell: Cell, topology: &Topology) -> Vec<Cell> {
+    self
+      .offsets()
+      .filter_map(|(dx, dy)| {
+        topology.normalize(Cell {
+          x: cell.x + dx,
+          y: cell.y + dy,
+        })
+      })
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn hexagonal_neighbors_skip_one_diagonal() {
+    let offsets: Vec<(i32, i32)> = Neighborhood::Hexagonal.offsets().collect();
+    assert_eq!(offsets.len(), 6);
+    assert!(!offsets.contains(&(1, -1)));
+    assert!(!offsets.contains(&(-1, 1)));
+    assert_eq!(
+      Neighborhood::VonNeumann.offsets().collect::<Vec<_>>(),
+      vec![(0, -1), (1, 0), (0, 1), (-1, 0)]
+    );
+  }
+}
@@ -0,0 +1,197 @@
+use crate::life::engine::bounding_box;
+use crate::life::CellStates;
+use lexicon::Cell;
+use std::fmt;
+
+/// A pattern as found in a file, along with what the file says about it.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Pattern {
+  pub name: Option<String>,
+  pub author: Option<String>,
+  pub comments: Vec<String>,
+  /// The rule it runs in, as written in the file.
+  pub rule: Option<String>,
+  pub cells: CellStates,
+}
+
+/// The file formats of patterns.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+  Rle,
+  /// The `.cells` format of LifeWiki.
+  Plaintext,
+  Life105,
+  Life106,
+  /// Golly’s quadtree format.
+  Macrocell,
+}
+
+impl Format {
+  pub const ALL: [Format; 5] = [
+    Format::Rle,
+    Format::Plaintext,
+    Format::Life105,
+    Format::Life106,
+    Format::Macrocell,
+  ];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Format::Rle => "RLE",
+      Format::Plaintext => "Plaintext",
+      Format::Life105 => "Life 1.05",
+      Format::Life106 => "Life 1.06",
+      Format::Macrocell => "Macrocell",
+    }
+  }
+
+  pub fn extension(&self) -> &'static str {
+    match self {
+      Format::Rle => "rle",
+      Format::Plaintext => "cells",
+      Format::Life105 | Format::Life106 => "lif",
+      Format::Macrocell => "mc",
+    }
+  }
+
+  /// Guesses the format of a pattern from its contents.
+  pub fn detect(text: &str) -> Format {
+    let lines: Vec<&str> = text
+      .lines()
+      .map(str::trim)
+      .filter(|line| !line.is_empty())
+      .collect();
+    match lines.first() {
+      Some(line) if line.starts_with("#Life 1.06") => return Format::Life106,
+      Some(line) if line.starts_with("#Life 1.05") => return Format::Life105,
+      Some(line) if line.starts_with('!') => return Format::Plaintext,
+      Some(line) if line.starts_with("[M2]") => return Format::Macrocell,
+      _ => {}
+    }
+    let rows: Vec<&str> = lines
+      .iter()
+      .copied()
+      .filter(|line| !line.starts_with('#'))
+      .collect();
+    let is_row = |row: &&str| row.chars().all(|c| matches!(c, '.' | 'O' | '*'));
+    let is_coordinates = |row: &&str| {
+      let coordinates: Vec<&str> = row.split_whitespace().collect();
+      coordinates.len() == 2
+        && coordinates
+          .iter()
+          .all(|coordinate| coordinate.parse::<i32>().is_ok())
+    };
+    if !rows.is_empty() && rows.iter().all(is_row) {
+      if lines.iter().any(|line| line.starts_with("#P")) {
+        Format::Life105
+      } else {
+        Format::Plaintext
+      }
+    } else if !rows.is_empty() && rows.iter().all(is_coordinates) {
+      Format::Life106
+    } else {
+      Format::Rle
+    }
+  }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParsePatternError(pub(crate) String);
+
+impl fmt::Display for ParsePatternError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+impl Pattern {
+  pub fn new(cells: CellStates) -> Self {
+    Self {
+      cells,
+      ..Self::default()
+    }
+  }
+
+  /// Reads a pattern in any format, guessing which one from the contents,
+  /// or the object of an apgcode.
+  pub fn parse(text: &str) -> Result<Self, ParsePatternError> {
+    let apgcode = text.trim();
+    if ["xs", "xp", "xq"]
+      .iter()
+      .any(|prefix| apgcode.starts_with(prefix))
+    {
+      return Pattern::from_apgcode(apgcode);
+    }
+    match Format::detect(text) {
+      Format::Rle => Pattern::from_rle(text),
+      Format::Plaintext => Pattern::from_plaintext(text),
+      Format::Life105 => Pattern::from_life_105(text),
+      Format::Life106 => Pattern::from_life_106(text),
+      Format::Macrocell => Pattern::from_macrocell(text),
+    }
+  }
+
+  pub fn write(&self, format: Format) -> String {
+    match format {
+      Format::Rle => self.to_rle(),
+      Format::Plaintext => self.to_plaintext(),
+      Format::Life105 => self.to_life_105(),
+      Format::Life106 => self.to_life_106(),
+      Format::Macrocell => self.to_macrocell(),
+    }
+  }
+
+  pub fn bounding_box(&self) -> Option<(Cell, Cell)> {
+    bounding_box(self.cells.keys().copied())
+  }
+
+  /// The states of the cells row by row, from the top left corner of the
+  /// pattern, without the dead cells ending the rows.
+  pub(crate) fn rows(&self) -> Vec<Vec<u8>> {
+    let (top_left, bottom_right) = match self.bounding_box() {
+      Some(bounds) => bounds,
+      None => return vec![],
+    };
+    let width = (bottom_right.x - top_left.x + 1) as usize;
+    let mut rows = vec![vec![0; width]; (bottom_right.y - top_left.y + 1) as usize];
+    for (cell, &state) in &self.cells {
+      rows[(cell.y - top_left.y) as usize][(cell.x - top_left.x) as usize] = state;
+    }
+    for row in rows.iter_mut() {
+      while row.last() == Some(&0) {
+        row.pop();
+      }
+    }
+    rows
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn detects_formats() {
+    let formats = [
+      ("#N Glider\nx = 3, y = 3\nbo$2bo$3o!", Format::Rle),
+      ("bo$2bo$3o!", Format::Rle),
+      ("!Name: Glider\n.O\n..O\nOOO", Format::Plaintext),
+      (".O\n..O\nOOO", Format::Plaintext),
+      ("#Life 1.05\n#P -1 -1\n.*\n..*\n***", Format::Life105),
+      ("#D Glider\n#P -1 -1\n.*\n..*\n***", Format::Life105),
+      ("#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1", Format::Life106),
+      ("0 -1\n1 0\n-1 1\n0 1\n1 1", Format::Life106),
+      (
+        "[M2] (golly 4.2)\n.*$..*$***$\n4 0 0 0 1",
+        Format::Macrocell,
+      ),
+    ];
+    for (text, format) in formats {
+      assert_eq!(Format::detect(text), format, "{}", text);
+      assert_eq!(Pattern::parse(text).unwrap().cells.len(), 5);
+    }
+    let glider = Pattern::parse(" xq4_153\n").unwrap();
+    assert_eq!(glider.name, Some("glider".to_string()));
+    assert_eq!(glider.cells.len(), 5);
+  }
+}
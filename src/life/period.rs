@@ -0,0 +1,233 @@
+use crate::life::engine::bounding_box;
+use crate::life::CellStates;
+use lexicon::Cell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// How many generations are remembered before giving up on finding a period.
+const MAX_HISTORY: usize = 100_000;
+
+/// What is known about the evolution of a pattern so far.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Periodicity {
+  NotYetPeriodic,
+  Empty,
+  StillLife,
+  Oscillator {
+    period: u64,
+  },
+  /// A pattern that comes back moved by `offset` cells after `period`
+  /// generations.
+  Spaceship {
+    period: u64,
+    offset: (i32, i32),
+  },
+}
+
+impl fmt::Display for Periodicity {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Periodicity::NotYetPeriodic => write!(f, "not yet periodic"),
+      Periodicity::Empty => write!(f, "empty"),
+      Periodicity::StillLife => write!(f, "still life"),
+      Periodicity::Oscillator { period } => write!(f, "period-{} oscillator", period),
+      Periodicity::Spaceship { period, offset } => {
+        let (dx, dy) = (offset.0.unsigned_abs(), offset.1.unsigned_abs());
+        let (dx, dy) = (dx.max(dy), dx.min(dy));
+        let divisor = gcd(gcd(dx, dy), *period as u32);
+        let period = *period as u32 / divisor;
+        let (dx, dy) = (dx / divisor, dy / divisor);
+        // Oblique speeds give both displacements instead of the multiple of c
+        let per = if period == 1 {
+          String::new()
+        } else {
+          format!("/{}", period)
+        };
+        let multiple = if dx == 1 {
+          String::new()
+        } else {
+          dx.to_string()
+        };
+        if dy == 0 {
+          write!(f, "{}c{} orthogonal spaceship", multiple, per)
+        } else if dx == dy {
+          write!(f, "{}c{} diagonal spaceship", multiple, per)
+        } else if dx == 2 * dy {
+          write!(f, "({},{})c{} knightship", dx, dy, per)
+        } else {
+          write!(f, "({},{})c{} oblique spaceship", dx, dy, per)
+        }
+      }
+    }
+  }
+}
+
+impl Periodicity {
+  /// How many cells a spaceship moves at each generation on average.
+  pub fn velocity(&self) -> Option<(f64, f64)> {
+    match self {
+      Periodicity::Spaceship { period, offset } => Some((
+        offset.0 as f64 / *period as f64,
+        offset.1 as f64 / *period as f64,
+      )),
+      _ => None,
+    }
+  }
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+  if b == 0 {
+    a
+  } else {
+    gcd(b, a % b)
+  }
+}
+
+/// Finds when a pattern repeats, possibly somewhere else, by remembering a
+/// hash of the shape of every generation along with where it was.
+pub struct PeriodDetector {
+  seen: HashMap<u64, (u64, Cell)>,
+  periodicity: Periodicity,
+}
+
+impl Default for PeriodDetector {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl PeriodDetector {
+  pub fn new() -> Self {
+    Self {
+      seen: HashMap::new(),
+      periodicity: Periodicity::NotYetPeriodic,
+    }
+  }
+
+  pub fn periodicity(&self) -> Periodicity {
+    self.periodicity
+  }
+
+  /// Remembers the cells of a generation, which must directly follow the
+  /// previously recorded one.
+  pub fn record(&mut self, generation: u64, cells: &CellStates) -> Periodicity {
+    if self.periodicity != Periodicity::NotYetPeriodic || self.seen.len() >= MAX_HISTORY {
+      return self.periodicity;
+    }
+    let (top_left, _) = match bounding_box(cells.keys().copied()) {
+      Some(bounds) => bounds,
+      None => {
+        self.periodicity = Periodicity::Empty;
+        return self.periodicity;
+      }
+    };
+    let hash = shape_hash(cells, top_left);
+    if let Some(&(previous, previous_top_left)) = self.seen.get(&hash) {
+      let period = generation - previous;
+      let offset = (
+        top_left.x - previous_top_left.x,
+        top_left.y - previous_top_left.y,
+      );
+      self.periodicity = match (period, offset) {
+        (1, (0, 0)) => Periodicity::StillLife,
+        (_, (0, 0)) => Periodicity::Oscillator { period },
+        _ => Periodicity::Spaceship { period, offset },
+      };
+    } else {
+      self.seen.insert(hash, (generation, top_left));
+    }
+    self.periodicity
+  }
+}
+
+/// A hash of the cells that doesn't depend on where they are.
+fn shape_hash(cells: &CellStates, top_left: Cell) -> u64 {
+  let mut shape: Vec<(i32, i32, u8)> = cells
+    .iter()
+    .map(|(cell, &state)| (cell.y - top_left.y, cell.x - top_left.x, state))
+    .collect();
+  shape.sort_unstable();
+  let mut hasher = DefaultHasher::new();
+  shape.hash(&mut hasher);
+  hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::life::{from_alive_cells, tick, Rule};
+
+  fn detect(cells: &[(i32, i32)], generations: u64) -> Periodicity {
+    let rule = Rule::conway();
+    let mut cells = cells.iter().map(|&(x, y)| Cell { x, y }).collect();
+    let mut detector = PeriodDetector::new();
+    for generation in 0..generations {
+      detector.record(generation, &from_alive_cells(&cells));
+      cells = tick(&cells, &rule);
+    }
+    detector.periodicity()
+  }
+
+  #[test]
+  fn finds_still_lifes_and_oscillators() {
+    assert_eq!(
+      detect(&[(0, 0), (1, 0), (0, 1), (1, 1)], 2),
+      Periodicity::StillLife
+    );
+    assert_eq!(
+      detect(&[(0, 0), (1, 0), (2, 0)], 3),
+      Periodicity::Oscillator { period: 2 }
+    );
+    assert_eq!(detect(&[(0, 0), (1, 0)], 2), Periodicity::Empty);
+  }
+
+  #[test]
+  fn finds_spaceships_whose_phases_change_shape() {
+    let lwss = [
+      (1, 0),
+      (4, 0),
+      (0, 1),
+      (0, 2),
+      (4, 2),
+      (0, 3),
+      (1, 3),
+      (2, 3),
+      (3, 3),
+    ];
+    let periodicity = detect(&lwss, 5);
+    assert_eq!(
+      periodicity,
+      Periodicity::Spaceship {
+        period: 4,
+        offset: (-2, 0)
+      }
+    );
+    assert_eq!(periodicity.velocity(), Some((-0.5, 0.0)));
+  }
+
+  #[test]
+  fn formats_speeds_in_c_notation() {
+    let spaceship = |period, offset| Periodicity::Spaceship { period, offset }.to_string();
+    assert_eq!(spaceship(4, (1, 1)), "c/4 diagonal spaceship");
+    assert_eq!(spaceship(4, (-2, 0)), "c/2 orthogonal spaceship");
+    assert_eq!(spaceship(7, (0, 2)), "2c/7 orthogonal spaceship");
+    assert_eq!(spaceship(12, (-2, -4)), "(2,1)c/6 knightship");
+    assert_eq!(spaceship(45, (17, 17)), "17c/45 diagonal spaceship");
+    assert_eq!(spaceship(10, (3, 1)), "(3,1)c/10 oblique spaceship");
+  }
+
+  #[test]
+  fn finds_translated_repeats() {
+    let glider = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+    assert_eq!(detect(&glider, 4), Periodicity::NotYetPeriodic);
+    assert_eq!(
+      detect(&glider, 5),
+      Periodicity::Spaceship {
+        period: 4,
+        offset: (1, 1)
+      }
+    );
+  }
+}
@@ -0,0 +1,105 @@
+use crate::life::{ParsePatternError, Pattern};
+use lexicon::Cell;
+
+impl Pattern {
+  /// Reads a pattern in the plaintext format of `.cells` files: `!` comment
+  /// lines, the first ones possibly giving the name and author, then rows of
+  /// `.` for dead cells and `O` for alive ones.
+  pub fn from_plaintext(text: &str) -> Result<Self, ParsePatternError> {
+    let mut pattern = Pattern::default();
+    let mut y = 0;
+    for line in text.lines().map(str::trim_end) {
+      if let Some(comment) = line.strip_prefix('!') {
+        let comment = comment.trim();
+        if let Some(name) = comment.strip_prefix("Name:") {
+          pattern.name = Some(name.trim().to_string());
+        } else if let Some(author) = comment.strip_prefix("Author:") {
+          pattern.author = Some(author.trim().to_string());
+        } else {
+          pattern.comments.push(comment.to_string());
+        }
+        continue;
+      }
+      for (x, c) in line.chars().enumerate() {
+        match c {
+          '.' => {}
+          'O' | '*' => {
+            pattern.cells.insert(Cell { x: x as i32, y }, 1);
+          }
+          _ => {
+            return Err(ParsePatternError(format!(
+              "Unexpected '{}' in plaintext",
+              c
+            )))
+          }
+        }
+      }
+      y += 1;
+    }
+    Ok(pattern)
+  }
+
+  /// Writes the alive cells of the pattern in the plaintext format.
+  pub fn to_plaintext(&self) -> String {
+    let mut text = String::new();
+    if let Some(name) = &self.name {
+      text.push_str(&format!("!Name: {}\n", name));
+    }
+    if let Some(author) = &self.author {
+      text.push_str(&format!("!Author: {}\n", author));
+    }
+    for comment in &self.comments {
+      text.push_str(&format!("!{}\n", comment));
+    }
+    for row in self.rows() {
+      let mut line: String = row
+        .iter()
+        .map(|&state| if state == 1 { 'O' } else { '.' })
+        .collect::<String>()
+        .trim_end_matches('.')
+        .to_string();
+      // Empty lines would be ignored by some readers
+      if line.is_empty() {
+        line.push('.');
+      }
+      text.push_str(&line);
+      text.push('\n');
+    }
+    text
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::life::CellStates;
+
+  fn rows(lines: &[&str]) -> CellStates {
+    Pattern::from_plaintext(&lines.join("\n")).unwrap().cells
+  }
+
+  #[test]
+  fn reads_plaintext() {
+    let pattern = Pattern::from_plaintext(
+      "!Name: Glider\n!Author: Richard K. Guy\n!The smallest spaceship.\n.O\n..O\nOOO\n",
+    )
+    .unwrap();
+    assert_eq!(pattern.name, Some("Glider".to_string()));
+    assert_eq!(pattern.author, Some("Richard K. Guy".to_string()));
+    assert_eq!(
+      pattern.comments,
+      vec!["The smallest spaceship.".to_string()]
+    );
+    assert_eq!(pattern.cells, rows(&[".*", "..*", "***"]));
+    assert!(Pattern::from_plaintext("!Name: Oops\n.o\n").is_err());
+  }
+
+  #[test]
+  fn writes_plaintext_back() {
+    let mut pattern = Pattern::new(rows(&["OO", "", "", "O.O"]));
+    pattern.name = Some("Something".to_string());
+    let text = pattern.to_plaintext();
+    assert_eq!(text, "!Name: Something\nOO\n.\n.\nO.O\n");
+    assert_eq!(Pattern::from_plaintext(&text).unwrap(), pattern);
+  }
+}
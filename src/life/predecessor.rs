@@ -0,0 +1,169 @@
+use crate::life::engine::bounding_box;
+use crate::life::sat::{Solution, Solver};
+use crate::life::{CellSet, Rule, Transitions, NEIGHBORS};
+use lexicon::Cell;
+use std::collections::HashMap;
+
+/// Patterns whose search area would be bigger than this aren’t searched.
+const MAX_CELLS: usize = 48 * 48;
+
+/// The result of looking for the previous generation of a pattern.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Predecessor {
+  Found(CellSet),
+  /// No pattern within the search area evolves into the given one, which
+  /// makes it a Garden of Eden if the area is big enough.
+  Impossible,
+  /// The search area would have been too big.
+  TooBig,
+  /// The search gave up after too many conflicts.
+  GaveUp,
+}
+
+impl Predecessor {
+  /// Whether a rule only looks at the nearest neighbors of two-state cells
+  /// on an infinite plane, as the search expects.
+  pub fn supports(rule: &Rule) -> bool {
+    rule.states == 2
+      && !rule.topology.is_bounded()
+      && !matches!(rule.transitions, Transitions::Extended(_))
+  }
+}
+
+/// Looks for cells that evolve into the given ones in one generation, up to
+/// `margin` cells away from them. Each cell of that area is a variable of a
+/// SAT problem, and each cell that can be affected by them gets clauses
+/// forbidding the neighborhoods that wouldn’t give its expected state.
+pub fn predecessor(cells: &CellSet, rule: &Rule, margin: i32, max_conflicts: u64) -> Predecessor {
+  let (top_left, bottom_right) = match bounding_box(cells.iter().copied()) {
+    Some(bounds) => bounds,
+    None => return Predecessor::Found(CellSet::new()),
+  };
+  let (left, top) = (top_left.x - margin, top_left.y - margin);
+  let (right, bottom) = (bottom_right.x + margin, bottom_right.y + margin);
+  let (width, height) = ((right - left + 1) as usize, (bottom - top + 1) as usize);
+  if width * height > MAX_CELLS {
+    return Predecessor::TooBig;
+  }
+
+  let mut variables: HashMap<Cell, i32> = HashMap::new();
+  for y in top..=bottom {
+    for x in left..=right {
+      variables.insert(Cell { x, y }, variables.len() as i32 + 1);
+    }
+  }
+  let mut solver = Solver::new(variables.len());
+  for y in top - 1..=bottom + 1 {
+    for x in left - 1..=right + 1 {
+      let cell = Cell { x, y };
+      let expected = cells.contains(&cell);
+      // The cell itself, then its neighbors in the order of their bits
+      let inputs: Vec<Option<i32>> = std::iter::once((0, 0))
+        .chain(NEIGHBORS.iter().copied())
+        .map(|(dx, dy)| {
+          variables
+            .get(&Cell {
+              x: x + dx,
+              y: y + dy,
+            })
+            .copied()
+        })
+        .collect();
+      let unknowns: Vec<(usize, i32)> = inputs
+        .iter()
+        .enumerate()
+        .filter_map(|(i, variable)| variable.map(|variable| (i, variable)))
+        .collect();
+      for assignment in 0..1_u32 << unknowns.len() {
+        let mut alive = false;
+        let mut neighborhood = 0_u8;
+        for (bit, &(i, _)) in unknowns.iter().enumerate() {
+          if assignment & 1 << bit != 0 {
+            if i == 0 {
+              alive = true;
+            } else {
+              neighborhood |= 1 << (i - 1);
+            }
+          }
+        }
+        if rule.next_state(alive, neighborhood) != expected {
+          let clause: Vec<i32> = unknowns
+            .iter()
+            .enumerate()
+            .map(|(bit, &(_, variable))| {
+              if assignment & 1 << bit != 0 {
+                -variable
+              } else {
+                variable
+              }
+            })
+            .collect();
+          solver.add_clause(&clause);
+        }
+      }
+    }
+  }
+
+  match solver.solve(max_conflicts) {
+    Solution::Satisfiable(values) => Predecessor::Found(
+      variables
+        .into_iter()
+        .filter(|&(_, variable)| values[variable as usize - 1])
+        .map(|(cell, _)| cell)
+        .collect(),
+    ),
+    Solution::Unsatisfiable => Predecessor::Impossible,
+    Solution::Unknown => Predecessor::GaveUp,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::life::tick;
+
+  fn cells(cells: &[(i32, i32)]) -> CellSet {
+    cells.iter().map(|&(x, y)| Cell { x, y }).collect()
+  }
+
+  #[test]
+  fn finds_predecessors() {
+    let rule = Rule::conway();
+    for pattern in [
+      cells(&[(0, 0), (1, 0), (2, 0)]),
+      cells(&[(0, 0)]),
+      cells(&[(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]),
+    ] {
+      match predecessor(&pattern, &rule, 1, 100_000) {
+        Predecessor::Found(parent) => assert_eq!(tick(&parent, &rule), pattern),
+        result => panic!("{:?}", result),
+      }
+    }
+  }
+
+  #[test]
+  fn proves_there_is_no_predecessor_in_the_area() {
+    let rule = Rule::conway();
+    assert_eq!(
+      predecessor(&cells(&[(0, 0)]), &rule, 0, 100_000),
+      Predecessor::Impossible
+    );
+    let seeds = "B2/S".parse().unwrap();
+    // Under Seeds, alive cells never survive, so a block needs the cells
+    // around it to be born, which then also give birth to their neighbors
+    assert_eq!(
+      predecessor(
+        &cells(&[(0, 0), (1, 0), (0, 1), (1, 1)]),
+        &seeds,
+        1,
+        100_000
+      ),
+      Predecessor::Impossible
+    );
+    let diagonal = (0..100).map(|i| Cell { x: i, y: i }).collect();
+    assert_eq!(
+      predecessor(&diagonal, &rule, 1, 100_000),
+      Predecessor::TooBig
+    );
+  }
+}
@@ -0,0 +1,350 @@
+use crate::life::{CellStates, ParsePatternError, Pattern};
+use lexicon::Cell;
+
+/// Lines of cells aren’t written longer than this.
+const MAX_LINE_LENGTH: usize = 70;
+
+/// Patterns with more cells than this aren’t read, as they wouldn’t fit in
+/// memory.
+const MAX_CELLS: usize = 4_000_000;
+
+/// The letters of a state in multi-state RLE: `.` for dead cells, `A` to
+/// `X` for the states 1 to 24, then `pA` to `pX`, `qA`…, up to `yO` for 255.
+fn state_letters(state: u8, multi_state: bool) -> String {
+  match (state, multi_state) {
+    (0, false) => "b".to_string(),
+    (_, false) => "o".to_string(),
+    (0, true) => ".".to_string(),
+    (state, true) => {
+      let prefix = (state - 1) / 24;
+      let letter = (b'A' + (state - 1) % 24) as char;
+      if prefix == 0 {
+        letter.to_string()
+      } else {
+        format!("{}{}", (b'p' + prefix - 1) as char, letter)
+      }
+    }
+  }
+}
+
+/// Reads the `x = 3, y = 3, rule = B3/S23` header line, of which only the
+/// rule matters. The rule is last and runs to the end of the line, as it can
+/// hold commas itself, like in `B3/S23:T100,80`.
+fn parse_header(line: &str) -> Result<Option<String>, ParsePatternError> {
+  let mut fields = line;
+  while !fields.trim().is_empty() {
+    let invalid_field =
+      |field: &str| ParsePatternError(format!("Invalid RLE header field '{}'", field.trim()));
+    let (key, rest) = fields
+      .split_once('=')
+      .ok_or_else(|| invalid_field(fields.split(',').next().unwrap()))?;
+    if let Some((field, _)) = key.split_once(',') {
+      return Err(invalid_field(field));
+    }
+    if key.trim() == "rule" {
+      return Ok(Some(rest.trim().to_string()));
+    }
+    let (value, next) = rest.split_once(',').unwrap_or((rest, ""));
+    if let "x" | "y" = key.trim() {
+      value
+        .trim()
+        .parse::<u32>()
+        .map_err(|_| ParsePatternError(format!("Invalid RLE size '{}'", value.trim())))?;
+    }
+    fields = next;
+  }
+  Ok(None)
+}
+
+/// Reads the runs of cells, up to the final `!`, with at most `max_cells`
+/// of them.
+fn parse_cells(
+  data: &str,
+  (left, top): (i32, i32),
+  max_cells: usize,
+) -> Result<CellStates, ParsePatternError> {
+  let too_large = || ParsePatternError("RLE pattern too large".to_string());
+  let mut cells = CellStates::new();
+  let (mut x, mut y) = (left, top);
+  let mut count: Option<i32> = None;
+  let mut prefix: Option<u8> = None;
+  for c in data.chars() {
+    let state = match c {
+      '0'..='9' => {
+        let digit = c.to_digit(10).unwrap() as i32;
+        count = Some(
+          count
+            .unwrap_or(0)
+            .checked_mul(10)
+            .and_then(|count| count.checked_add(digit))
+            .ok_or_else(too_large)?,
+        );
+        continue;
+      }
+      '!' => break,
+      c if c.is_whitespace() => continue,
+      '$' => {
+        y = y
+          .checked_add(count.take().unwrap_or(1))
+          .ok_or_else(too_large)?;
+        x = left;
+        continue;
+      }
+      'p'..='y' if prefix.is_none() => {
+        prefix = Some(c as u8 - b'p' + 1);
+        continue;
+      }
+      'b' | '.' => 0,
+      'A'..='X' => {
+        let state = prefix.take().unwrap_or(0) as u32 * 24 + (c as u8 - b'A' + 1) as u32;
+        u8::try_from(state)
+          .map_err(|_| ParsePatternError(format!("Invalid RLE state {}", state)))?
+      }
+      // Other lowercase letters are alive cells in two-state patterns
+      'a'..='z' => 1,
+      _ => return Err(ParsePatternError(format!("Unexpected '{}' in RLE", c))),
+    };
+    if prefix.is_some() {
+      return Err(ParsePatternError(format!(
+        "Unexpected '{}' after a state prefix in RLE",
+        c
+      )));
+    }
+    let run = count.take().unwrap_or(1);
+    let end = x.checked_add(run).ok_or_else(too_large)?;
+    if state > 0 {
+      if cells.len() + run as usize > max_cells {
+        return Err(too_large());
+      }
+      for x in x..end {
+        cells.insert(Cell { x, y }, state);
+      }
+    }
+    x = end;
+  }
+  Ok(cells)
+}
+
+impl Pattern {
+  /// Reads a pattern in the RLE format: `#` comment lines, a header line
+  /// with the size and the rule, then the runs of cells.
+  pub fn from_rle(text: &str) -> Result<Self, ParsePatternError> {
+    Self::from_rle_within(text, MAX_CELLS)
+  }
+
+  /// Reads a pattern in the RLE format, if it has at most `max_cells` cells.
+  pub(crate) fn from_rle_within(text: &str, max_cells: usize) -> Result<Self, ParsePatternError> {
+    let mut pattern = Pattern::default();
+    let mut position = (0, 0);
+    let mut data = String::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+      if !data.is_empty() {
+        data.push_str(line);
+      } else if let Some(comment) = line.strip_prefix('#') {
+        let (tag, value) = comment.split_at(comment.chars().next().map_or(0, char::len_utf8));
+        let value = value.trim().to_string();
+        match tag {
+          "N" => pattern.name = Some(value),
+          "O" => pattern.author = Some(value),
+          "C" | "c" => pattern.comments.push(value),
+          "r" => pattern.rule = Some(value),
+          // The position of the top left corner
+          "R" | "P" => {
+            let coordinates: Vec<i32> = value
+              .split_whitespace()
+              .map(|coordinate| coordinate.parse())
+              .collect::<Result<_, _>>()
+              .map_err(|_| ParsePatternError(format!("Invalid RLE position '{}'", value)))?;
+            if let [x, y] = coordinates[..] {
+              position = (x, y);
+            }
+          }
+          _ => {}
+        }
+      } else if line.starts_with('x') && line.contains('=') {
+        if let Some(rule) = parse_header(line)? {
+          pattern.rule = Some(rule);
+        }
+      } else {
+        data.push_str(line);
+      }
+    }
+    if data.is_empty() {
+      return Err(ParsePatternError("No cells in RLE".to_string()));
+    }
+    pattern.cells = parse_cells(&data, position, max_cells)?;
+    Ok(pattern)
+  }
+
+  /// Writes the pattern in the RLE format, with multi-state letters if it
+  /// has more than two states.
+  pub fn to_rle(&self) -> String {
+    let mut rle = String::new();
+    if let Some(name) = &self.name {
+      rle.push_str(&format!("#N {}\n", name));
+    }
+    if let Some(author) = &self.author {
+      rle.push_str(&format!("#O {}\n", author));
+    }
+    for comment in &self.comments {
+      rle.push_str(&format!("#C {}\n", comment));
+    }
+    let (width, height) = self
+      .bounding_box()
+      .map_or((0, 0), |(top_left, bottom_right)| {
+        (
+          bottom_right.x - top_left.x + 1,
+          bottom_right.y - top_left.y + 1,
+        )
+      });
+    rle.push_str(&format!("x = {}, y = {}", width, height));
+    if let Some(rule) = &self.rule {
+      rle.push_str(&format!(", rule = {}", rule));
+    }
+    rle.push('\n');
+
+    let multi_state = self.cells.values().any(|&state| state > 1);
+    let mut tokens = vec![];
+    let mut empty_rows = 0;
+    for row in self.rows() {
+      if row.is_empty() {
+        empty_rows += 1;
+        continue;
+      }
+      if !tokens.is_empty() {
+        tokens.push(run(empty_rows + 1, "$"));
+      }
+      empty_rows = 0;
+      let mut x = 0;
+      while x < row.len() {
+        let length = row[x..]
+          .iter()
+          .take_while(|&&state| state == row[x])
+          .count();
+        tokens.push(run(length, &state_letters(row[x], multi_state)));
+        x += length;
+      }
+    }
+    tokens.push("!".to_string());
+
+    let mut line = String::new();
+    for token in tokens {
+      if line.len() + token.len() > MAX_LINE_LENGTH {
+        rle.push_str(&line);
+        rle.push('\n');
+        line.clear();
+      }
+      line.push_str(&token);
+    }
+    rle.push_str(&line);
+    rle.push('\n');
+    rle
+  }
+}
+
+fn run(length: usize, letters: &str) -> String {
+  if length == 1 {
+    letters.to_string()
+  } else {
+    format!("{}{}", length, letters)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn cells(cells: &[(i32, i32, u8)]) -> CellStates {
+    cells
+      .iter()
+      .map(|&(x, y, state)| (Cell { x, y }, state))
+      .collect()
+  }
+
+  #[test]
+  fn reads_rle() {
+    let pattern = Pattern::from_rle(
+      "#N Glider\n#O Richard K. Guy\n#C The smallest spaceship.\n#C www.conwaylife.com\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!",
+    )
+    .unwrap();
+    assert_eq!(pattern.name, Some("Glider".to_string()));
+    assert_eq!(pattern.author, Some("Richard K. Guy".to_string()));
+    assert_eq!(pattern.comments.len(), 2);
+    assert_eq!(pattern.rule, Some("B3/S23".to_string()));
+    assert_eq!(
+      pattern.cells,
+      cells(&[(1, 0, 1), (2, 1, 1), (0, 2, 1), (1, 2, 1), (2, 2, 1)])
+    );
+  }
+
+  #[test]
+  fn reads_runs_across_lines_and_positions() {
+    let pattern = Pattern::from_rle("#R -1 5\nx = 12, y = 3\n1\n2o$\n2$o2\nb!").unwrap();
+    assert_eq!(
+      pattern.cells.keys().map(|cell| (cell.x, cell.y)).max(),
+      Some((10, 5))
+    );
+    assert_eq!(pattern.cells.len(), 13);
+    assert!(pattern.cells.contains_key(&Cell { x: -1, y: 8 }));
+    assert!(Pattern::from_rle("x = 3, y = 3\nbo$2b?!").is_err());
+  }
+
+  #[test]
+  fn rejects_huge_runs() {
+    assert!(Pattern::from_rle("x = 1, y = 1\n99999999999o!").is_err());
+    assert!(Pattern::from_rle("x = 1, y = 1\n2000000000o!").is_err());
+    assert!(Pattern::from_rle("x = 1, y = 1\n2000000000b2000000000bo!").is_err());
+    assert!(Pattern::from_rle("x = 1, y = 1\n2000000000$o!").is_ok());
+    assert!(Pattern::from_rle("#é\n#Cé\nx = 1, y = 1\no!").is_ok());
+  }
+
+  #[test]
+  fn reads_multi_state_letters() {
+    let pattern = Pattern::from_rle("x = 4, y = 1, rule = WireWorld\n.AyO2C!").unwrap();
+    assert_eq!(
+      pattern.cells,
+      cells(&[(1, 0, 1), (2, 0, 255), (3, 0, 3), (4, 0, 3)])
+    );
+  }
+
+  #[test]
+  fn writes_rle_back() {
+    let mut pattern = Pattern::new(cells(&[
+      (1, 0, 1),
+      (2, 1, 1),
+      (0, 2, 1),
+      (1, 2, 1),
+      (2, 2, 1),
+      (0, 5, 1),
+    ]));
+    pattern.name = Some("Glider and a cell".to_string());
+    pattern.rule = Some("B3/S23".to_string());
+    let rle = pattern.to_rle();
+    assert_eq!(
+      rle,
+      "#N Glider and a cell\nx = 3, y = 6, rule = B3/S23\nbo$2bo$3o3$o!\n"
+    );
+    assert_eq!(Pattern::from_rle(&rle).unwrap(), pattern);
+
+    let states = Pattern::new(cells(&[(0, 0, 2), (3, 0, 1), (0, 1, 30)]));
+    let rle = states.to_rle();
+    assert_eq!(rle, "x = 4, y = 2\nB2.A$pF!\n");
+    assert_eq!(Pattern::from_rle(&rle).unwrap().cells, states.cells);
+
+    let long = Pattern::new((0..100).map(|x| (Cell { x: 2 * x, y: 0 }, 1)).collect());
+    let rle = long.to_rle();
+    assert!(rle.lines().all(|line| line.len() <= MAX_LINE_LENGTH));
+    assert_eq!(Pattern::from_rle(&rle).unwrap().cells, long.cells);
+  }
+
+  #[test]
+  fn keeps_commas_in_rules() {
+    for rule in ["B3/S23:T100,80", "R5,C0,M1,S34..58,B34..45,NM"] {
+      let mut pattern = Pattern::new(cells(&[(0, 0, 1), (1, 0, 1), (2, 0, 1)]));
+      pattern.rule = Some(rule.to_string());
+      let rle = pattern.to_rle();
+      assert_eq!(Pattern::from_rle(&rle).unwrap(), pattern);
+    }
+    assert!(Pattern::from_rle("x = 3, y, rule = B3/S23\n3o!").is_err());
+  }
+}
@@ -0,0 +1,363 @@
+use crate::life::table;
+use crate::life::{LargerThanLife, Neighborhood, Neighborhoods, Rgb, RuleTable, Topology};
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
+
+/// A rule and the universe it runs in.
+///
+/// With more than two `states`, it is a Generations rule: instead of dying
+/// right away, cells go through `states - 2` decay states, during which they
+/// don't count as alive neighbors and can't be born again.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Rule {
+  pub transitions: Transitions,
+  pub states: u8,
+  pub topology: Topology,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Transitions {
+  /// A rule in B/S notation: a dead cell is born when its neighborhood is in
+  /// `birth`, an alive cell survives when it is in `survival`. Neighborhoods
+  /// are usually given by their number of alive neighbors ("B3/S23"), but
+  /// can also be isotropic non-totalistic ("B2-a/S12") in the Moore
+  /// neighborhood.
+  LifeLike {
+    neighborhood: Neighborhood,
+    birth: Neighborhoods,
+    survival: Neighborhoods,
+  },
+  /// A rule on a larger neighborhood, where only the number of alive cells
+  /// matters.
+  Extended(LargerThanLife),
+  /// Any rule on the nearest neighbors, given by a Golly rule table or tree.
+  Table(Rc<RuleTable>),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseRuleError(pub(crate) String);
+
+impl fmt::Display for ParseRuleError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+impl Rule {
+  pub fn conway() -> Rule {
+    Rule {
+      transitions: Transitions::LifeLike {
+        neighborhood: Neighborhood::Moore,
+        birth: Neighborhoods::from_counts(&[3]),
+        survival: Neighborhoods::from_counts(&[2, 3]),
+      },
+      states: 2,
+      topology: Topology::Infinite,
+    }
+  }
+
+  /// Two states and at most eight neighbors on an infinite plane, which is
+  /// all the quadtree engine knows about.
+  pub fn is_life_like(&self) -> bool {
+    self.states == 2
+      && !self.topology.is_bounded()
+      && matches!(self.transitions, Transitions::LifeLike { .. })
+  }
+
+  /// Conway’s Life on any topology, which the lexicon patterns are for.
+  pub fn is_conway(&self) -> bool {
+    self.states == 2 && self.transitions == Rule::conway().transitions
+  }
+
+  /// Whether only the number of alive neighbors among the nearest ones
+  /// matters, as the tiled engine requires.
+  pub fn is_totalistic(&self) -> bool {
+    match &self.transitions {
+      Transitions::LifeLike {
+        birth, survival, ..
+      } => birth.is_totalistic() && survival.is_totalistic(),
+      Transitions::Extended(_) | Transitions::Table(_) => false,
+    }
+  }
+
+  /// The cells around the nearest ones that count as neighbors (Larger than
+  /// Life rules are drawn on a square grid).
+  pub fn neighborhood(&self) -> Neighborhood {
+    match &self.transitions {
+      Transitions::LifeLike { neighborhood, .. } => *neighborhood,
+      Transitions::Extended(_) => Neighborhood::Moore,
+      Transitions::Table(table) => table.neighborhood,
+    }
+  }
+
+  /// The colors that the rule gives to some of its states.
+  pub fn colors(&self) -> Vec<(u8, Rgb)> {
+    match &self.transitions {
+      Transitions::Table(table) => table.colors.clone(),
+      _ => vec![],
+    }
+  }
+
+  pub fn from_table(table: RuleTable, topology: Topology) -> Rule {
+    Rule {
+      states: table.states,
+      transitions: Transitions::Table(Rc::new(table)),
+      topology,
+    }
+  }
+
+  /// The next state of a cell, `neighborhood` being the bit mask of its
+  /// eight surrounding cells that are alive, clockwise from the north one.
+  /// Extended rules only look at the number of alive neighbors.
+  pub fn next_state(&self, alive: bool, neighborhood: u8) -> bool {
+    match &self.transitions {
+      Transitions::LifeLike {
+        neighborhood: kind,
+        birth,
+        survival,
+      } => {
+        let neighborhood = neighborhood & kind.mask();
+        if alive {
+          survival.contains(neighborhood)
+        } else {
+          birth.contains(neighborhood)
+        }
+      }
+      Transitions::Extended(rule) => rule.next_state(alive, neighborhood.count_ones()),
+      Transitions::Table(table) => {
+        let neighbors: Vec<u8> = (0..8)
+          .filter(|i| table.neighborhood.mask() & 1 << i != 0)
+          .map(|i| (neighborhood >> i) & 1)
+          .collect();
+        table.next_state(alive as u8, &neighbors) == 1
+      }
+    }
+  }
+}
+
+impl Default for Rule {
+  fn default() -> Self {
+    Rule::conway()
+  }
+}
+
+fn parse_states(states: &str) -> Result<u8, ParseRuleError> {
+  match states.trim().parse() {
+    Ok(states) if states >= 2 => Ok(states),
+    _ => Err(ParseRuleError(format!(
+      "Invalid number of states '{}', expected a number from 2 to 255",
+      states
+    ))),
+  }
+}
+
+impl FromStr for Rule {
+  type Err = ParseRuleError;
+
+  /// Parses "B3/S23" (in any order and case) as well as the older "23/3"
+  /// notation, where survival comes first. Generations rules have a third
+  /// part with the number of states: "B2/S345/C4" or "345/2/4". Both can be
+  /// followed by a topology like ":T100,80". Hexagonal and von Neumann
+  /// neighborhoods are given by a final H or V ("B2/S34H"). Larger than Life
+  /// rules use the "R5,C0,M1,S34..58,B34..45,NM" notation. Built-in rule
+  /// tables are found by name.
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let (s, topology) = match s.split_once(':') {
+      Some((s, topology)) => (s, topology.parse()?),
+      None => (s, Topology::Infinite),
+    };
+    let built_in = table::BUILT_IN.iter().find(|file| {
+      RuleTable::name_of(file).is_some_and(|name| name.eq_ignore_ascii_case(s.trim()))
+    });
+    if let Some(file) = built_in {
+      return Ok(Rule::from_table(RuleTable::parse(file)?, topology));
+    }
+    if s.trim().starts_with(['R', 'r']) {
+      let (rule, states) = LargerThanLife::parse(s)?;
+      return Ok(Rule {
+        transitions: Transitions::Extended(rule),
+        states,
+        topology,
+      });
+    }
+    let s = s.trim();
+    let (s, neighborhood) = match s.chars().last().map(|c| c.to_ascii_uppercase()) {
+      Some('H') => (&s[..s.len() - 1], Neighborhood::Hexagonal),
+      Some('V') => (&s[..s.len() - 1], Neighborhood::VonNeumann),
+      _ => (s, Neighborhood::Moore),
+    };
+    let parts: Vec<&str> = s.split('/').collect();
+    if parts.len() != 2 && parts.len() != 3 {
+      return Err(ParseRuleError(
+        "A rule must look like \"B3/S23\", \"23/3\" or \"B2/S345/C4\"".to_string(),
+      ));
+    }
+
+    let mut birth = None;
+    let mut survival = None;
+    let mut states = 2;
+    for (i, part) in parts.iter().enumerate() {
+      let part = part.trim();
+      match part.chars().next().map(|c| c.to_ascii_uppercase()) {
+        Some('B') => birth = Some(Neighborhoods::parse(&part[1..])?),
+        Some('S') => survival = Some(Neighborhoods::parse(&part[1..])?),
+        Some('C' | 'G') => states = parse_states(&part[1..])?,
+        _ if i == 0 => survival = Some(Neighborhoods::parse(part)?),
+        _ if i == 1 => birth = Some(Neighborhoods::parse(part)?),
+        _ => states = parse_states(part)?,
+      }
+    }
+
+    let fits = |neighborhoods: &Neighborhoods| {
+      neighborhood == Neighborhood::Moore
+        || neighborhoods.is_totalistic()
+          && (neighborhood.size() + 1..=8).all(|count| !neighborhoods.contains_count(count))
+    };
+    match (birth, survival) {
+      (Some(birth), _) if birth.contains(0) => Err(ParseRuleError(
+        "Rules with B0 are not supported".to_string(),
+      )),
+      (Some(birth), Some(survival)) if !fits(&birth) || !fits(&survival) => {
+        Err(ParseRuleError(format!(
+          "Only the numbers of neighbors from 0 to {} are supported in this neighborhood",
+          neighborhood.size()
+        )))
+      }
+      (Some(birth), Some(survival)) => Ok(Rule {
+        transitions: Transitions::LifeLike {
+          neighborhood,
+          birth,
+          survival,
+        },
+        states,
+        topology,
+      }),
+      _ => Err(ParseRuleError(
+        "A rule needs both a B and an S part".to_string(),
+      )),
+    }
+  }
+}
+
+impl fmt::Display for Rule {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match &self.transitions {
+      Transitions::LifeLike {
+        neighborhood,
+        birth,
+        survival,
+      } => {
+        write!(f, "B{}/S{}", birth, survival)?;
+        if self.states > 2 {
+          write!(f, "/C{}", self.states)?;
+        }
+        write!(f, "{}", neighborhood.suffix())?;
+      }
+      Transitions::Extended(rule) => rule.format(f, self.states)?,
+      Transitions::Table(table) => write!(f, "{}", table.name)?,
+    }
+    write!(f, "{}", self.topology)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_conway() {
+    assert_eq!("B3/S23".parse(), Ok(Rule::conway()))
+  }
+
+  #[test]
+  fn parses_lowercase_and_swapped_parts() {
+    assert_eq!("s23/b3".parse(), Ok(Rule::conway()))
+  }
+
+  #[test]
+  fn parses_survival_first_notation() {
+    assert_eq!("23/3".parse(), Ok(Rule::conway()))
+  }
+
+  #[test]
+  fn parses_empty_survival() {
+    let rule: Rule = "B2/S".parse().unwrap();
+    assert_eq!(rule.to_string(), "B2/S")
+  }
+
+  #[test]
+  fn formats_day_and_night() {
+    let rule: Rule = "B3678/S34678".parse().unwrap();
+    assert_eq!(rule.to_string(), "B3678/S34678")
+  }
+
+  #[test]
+  fn parses_generations() {
+    let rule: Rule = "/2/3".parse().unwrap();
+    assert_eq!(rule.states, 3);
+    assert_eq!(rule.to_string(), "B2/S/C3");
+    assert_eq!("B2/S345/C4".parse(), "345/2/4".parse::<Rule>())
+  }
+
+  #[test]
+  fn parses_isotropic_non_totalistic() {
+    let rule: Rule = "B3/S2-i34q".parse().unwrap();
+    assert!(!rule.is_totalistic());
+    assert_eq!(rule.to_string(), "B3/S2-i34q")
+  }
+
+  #[test]
+  fn parses_topology_suffix() {
+    let rule: Rule = "B36/S23:T100,80".parse().unwrap();
+    assert_eq!(
+      rule.topology,
+      Topology::Torus {
+        width: 100,
+        height: 80
+      }
+    );
+    assert_eq!(rule.to_string(), "B36/S23:T100,80")
+  }
+
+  #[test]
+  fn parses_hexagonal_and_von_neumann() {
+    let rule: Rule = "B2/S34H".parse().unwrap();
+    assert_eq!(rule.neighborhood(), Neighborhood::Hexagonal);
+    assert_eq!(rule.to_string(), "B2/S34H");
+    let rule: Rule = "23/3v".parse().unwrap();
+    assert_eq!(rule.to_string(), "B3/S23V");
+    assert!("B2/S37H".parse::<Rule>().is_err());
+    assert!("B2a/S34H".parse::<Rule>().is_err())
+  }
+
+  #[test]
+  fn parses_larger_than_life() {
+    let rule: Rule = "R5,C0,M1,S34..58,B34..45,NM:T100,80".parse().unwrap();
+    assert!(!rule.is_life_like());
+    assert_eq!(rule.to_string(), "R5,C0,M1,S34..58,B34..45,NM:T100,80")
+  }
+
+  #[test]
+  fn finds_built_in_tables() {
+    let rule: Rule = "wireworld:T20,20".parse().unwrap();
+    assert_eq!(rule.states, 4);
+    assert_eq!(rule.to_string(), "WireWorld:T20,20")
+  }
+
+  #[test]
+  fn rejects_invalid_digits() {
+    assert!("B39/S23".parse::<Rule>().is_err())
+  }
+
+  #[test]
+  fn rejects_missing_part() {
+    assert!("B3".parse::<Rule>().is_err());
+    assert!("B3/B2".parse::<Rule>().is_err())
+  }
+
+  #[test]
+  fn rejects_b0() {
+    assert!("B03/S23".parse::<Rule>().is_err())
+  }
+}
@@ -0,0 +1,429 @@
+/// What a search found out about the clauses.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Solution {
+  /// A value for each variable that satisfies all the clauses.
+  Satisfiable(Vec<bool>),
+  Unsatisfiable,
+  /// The search gave up after too many conflicts.
+  Unknown,
+}
+
+/// A literal is a variable index times two, plus one if it is negated.
+type Literal = u32;
+
+fn variable(literal: Literal) -> usize {
+  (literal >> 1) as usize
+}
+
+fn value(values: &[Option<bool>], literal: Literal) -> Option<bool> {
+  values[variable(literal)].map(|value| value != (literal & 1 == 1))
+}
+
+/// The unassigned variables, as a binary heap ordered by activity so that
+/// the most active one is found without going through all of them.
+struct VariableOrder {
+  heap: Vec<usize>,
+  /// Where each variable is in the heap, if it is there.
+  positions: Vec<Option<usize>>,
+}
+
+impl VariableOrder {
+  fn new(variables: usize) -> Self {
+    Self {
+      heap: (0..variables).collect(),
+      positions: (0..variables).map(Some).collect(),
+    }
+  }
+
+  fn contains(&self, variable: usize) -> bool {
+    self.positions[variable].is_some()
+  }
+
+  fn insert(&mut self, variable: usize, activity: &[f64]) {
+    if self.contains(variable) {
+      return;
+    }
+    self.positions[variable] = Some(self.heap.len());
+    self.heap.push(variable);
+    self.sift_up(self.heap.len() - 1, activity);
+  }
+
+  /// Moves a variable up after its activity increased.
+  fn update(&mut self, variable: usize, activity: &[f64]) {
+    if let Some(position) = self.positions[variable] {
+      self.sift_up(position, activity);
+    }
+  }
+
+  fn pop(&mut self, activity: &[f64]) -> Option<usize> {
+    let last = self.heap.pop()?;
+    if self.heap.is_empty() {
+      self.positions[last] = None;
+      return Some(last);
+    }
+    let top = std::mem::replace(&mut self.heap[0], last);
+    self.positions[top] = None;
+    self.positions[last] = Some(0);
+    self.sift_down(0, activity);
+    Some(top)
+  }
+
+  fn sift_up(&mut self, mut position: usize, activity: &[f64]) {
+    while position > 0 {
+      let parent = (position - 1) / 2;
+      if activity[self.heap[parent]] >= activity[self.heap[position]] {
+        break;
+      }
+      self.swap(parent, position);
+      position = parent;
+    }
+  }
+
+  fn sift_down(&mut self, mut position: usize, activity: &[f64]) {
+    loop {
+      let mut largest = position;
+      for child in [2 * position + 1, 2 * position + 2] {
+        if child < self.heap.len() && activity[self.heap[child]] > activity[self.heap[largest]] {
+          largest = child;
+        }
+      }
+      if largest == position {
+        return;
+      }
+      self.swap(largest, position);
+      position = largest;
+    }
+  }
+
+  fn swap(&mut self, a: usize, b: usize) {
+    self.heap.swap(a, b);
+    self.positions[self.heap[a]] = Some(a);
+    self.positions[self.heap[b]] = Some(b);
+  }
+}
+
+/// A small CDCL SAT solver: unit propagation with two watched literals,
+/// clause learning at the first unique implication point, VSIDS-like
+/// branching and restarts.
+pub struct Solver {
+  clauses: Vec<Vec<Literal>>,
+  /// The clauses watching each literal, visited when it becomes false.
+  watches: Vec<Vec<usize>>,
+  values: Vec<Option<bool>>,
+  levels: Vec<usize>,
+  reasons: Vec<Option<usize>>,
+  trail: Vec<Literal>,
+  /// Where each decision level starts on the trail.
+  trail_limits: Vec<usize>,
+  propagated: usize,
+  activity: Vec<f64>,
+  order: VariableOrder,
+  increment: f64,
+  phases: Vec<bool>,
+  unsatisfiable: bool,
+}
+
+impl Solver {
+  pub fn new(variables: usize) -> Self {
+    Self {
+      clauses: vec![],
+      watches: vec![vec![]; variables * 2],
+      values: vec![None; variables],
+      levels: vec![0; variables],
+      reasons: vec![None; variables],
+      trail: vec![],
+      trail_limits: vec![],
+      propagated: 0,
+      activity: vec![0.0; variables],
+      order: VariableOrder::new(variables),
+      increment: 1.0,
+      phases: vec![false; variables],
+      unsatisfiable: false,
+    }
+  }
+
+  /// Adds a clause, as in the DIMACS format: variables are numbered from 1,
+  /// and negative numbers are negated variables.
+  pub fn add_clause(&mut self, clause: &[i32]) {
+    let mut literals: Vec<Literal> = vec![];
+    for &literal in clause {
+      let literal = ((literal.unsigned_abs() - 1) << 1) | (literal < 0) as u32;
+      match value(&self.values, literal) {
+        Some(true) => return,
+        Some(false) => continue,
+        None if literals.contains(&(literal ^ 1)) => return,
+        None if !literals.contains(&literal) => literals.push(literal),
+        None => {}
+      }
+    }
+    match literals.len() {
+      0 => self.unsatisfiable = true,
+      1 => self.assign(literals[0], None),
+      _ => {
+        let index = self.clauses.len();
+        self.watches[literals[0] as usize].push(index);
+        self.watches[literals[1] as usize].push(index);
+        self.clauses.push(literals);
+      }
+    }
+  }
+
+  pub fn solve(&mut self, max_conflicts: u64) -> Solution {
+    if self.unsatisfiable || self.propagate().is_some() {
+      return Solution::Unsatisfiable;
+    }
+    let mut conflicts = 0;
+    let mut restart_limit = 100.0;
+    let mut conflicts_since_restart = 0;
+    loop {
+      if let Some(conflict) = self.propagate() {
+        if self.trail_limits.is_empty() {
+          return Solution::Unsatisfiable;
+        }
+        conflicts += 1;
+        conflicts_since_restart += 1;
+        if conflicts > max_conflicts {
+          self.backtrack(0);
+          return Solution::Unknown;
+        }
+        let (learnt, level) = self.analyze(conflict);
+        self.backtrack(level);
+        if learnt.len() == 1 {
+          self.assign(learnt[0], None);
+        } else {
+          let index = self.clauses.len();
+          self.watches[learnt[0] as usize].push(index);
+          self.watches[learnt[1] as usize].push(index);
+          self.assign(learnt[0], Some(index));
+          self.clauses.push(learnt);
+        }
+        self.increment /= 0.95;
+        if conflicts_since_restart as f64 > restart_limit {
+          self.backtrack(0);
+          conflicts_since_restart = 0;
+          restart_limit *= 1.5;
+        }
+      } else {
+        // Variables assigned since they were put back are skipped
+        let mut unassigned = None;
+        while let Some(variable) = self.order.pop(&self.activity) {
+          if self.values[variable].is_none() {
+            unassigned = Some(variable);
+            break;
+          }
+        }
+        match unassigned {
+          Some(variable) => {
+            self.trail_limits.push(self.trail.len());
+            let literal = (variable as Literal) << 1 | !self.phases[variable] as Literal;
+            self.assign(literal, None);
+          }
+          None => {
+            let solution = self
+              .values
+              .iter()
+              .map(|value| value == &Some(true))
+              .collect();
+            self.backtrack(0);
+            return Solution::Satisfiable(solution);
+          }
+        }
+      }
+    }
+  }
+
+  fn assign(&mut self, literal: Literal, reason: Option<usize>) {
+    let variable = variable(literal);
+    self.values[variable] = Some(literal & 1 == 0);
+    self.levels[variable] = self.trail_limits.len();
+    self.reasons[variable] = reason;
+    self.trail.push(literal);
+  }
+
+  /// Assigns the literals implied by the current ones, until a clause can’t
+  /// be satisfied anymore.
+  fn propagate(&mut self) -> Option<usize> {
+    while self.propagated < self.trail.len() {
+      let falsified = self.trail[self.propagated] ^ 1;
+      self.propagated += 1;
+      let mut watchers = std::mem::take(&mut self.watches[falsified as usize]);
+      let mut i = 0;
+      while i < watchers.len() {
+        let index = watchers[i];
+        let clause = &mut self.clauses[index];
+        if clause[0] == falsified {
+          clause.swap(0, 1);
+        }
+        if value(&self.values, clause[0]) == Some(true) {
+          i += 1;
+          continue;
+        }
+        let replacement =
+          (2..clause.len()).find(|&k| value(&self.values, clause[k]) != Some(false));
+        if let Some(k) = replacement {
+          clause.swap(1, k);
+          self.watches[clause[1] as usize].push(index);
+          watchers.swap_remove(i);
+          continue;
+        }
+        let unit = clause[0];
+        if value(&self.values, unit) == Some(false) {
+          self.watches[falsified as usize] = watchers;
+          return Some(index);
+        }
+        self.assign(unit, Some(index));
+        i += 1;
+      }
+      self.watches[falsified as usize] = watchers;
+    }
+    None
+  }
+
+  /// The clause learnt from a conflict, made of the negation of its first
+  /// unique implication point and of literals from earlier levels, and the
+  /// level to go back to.
+  fn analyze(&mut self, conflict: usize) -> (Vec<Literal>, usize) {
+    let level = self.trail_limits.len();
+    let mut learnt = vec![0];
+    let mut seen = vec![false; self.values.len()];
+    let mut pending = 0;
+    let mut clause = conflict;
+    let mut implied: Option<Literal> = None;
+    let mut index = self.trail.len();
+    loop {
+      let skip = implied.is_some() as usize;
+      for k in skip..self.clauses[clause].len() {
+        let literal = self.clauses[clause][k];
+        let variable = variable(literal);
+        if !seen[variable] && self.levels[variable] > 0 {
+          seen[variable] = true;
+          self.bump(variable);
+          if self.levels[variable] == level {
+            pending += 1;
+          } else {
+            learnt.push(literal);
+          }
+        }
+      }
+      loop {
+        index -= 1;
+        if seen[variable(self.trail[index])] {
+          break;
+        }
+      }
+      let literal = self.trail[index];
+      seen[variable(literal)] = false;
+      implied = Some(literal);
+      pending -= 1;
+      if pending == 0 {
+        learnt[0] = literal ^ 1;
+        break;
+      }
+      clause = self.reasons[variable(literal)].expect("implied literal to have a reason");
+    }
+    // The literal of the latest level other than the current one is watched
+    let mut backtrack_level = 0;
+    for k in 1..learnt.len() {
+      let literal_level = self.levels[variable(learnt[k])];
+      if literal_level > backtrack_level {
+        backtrack_level = literal_level;
+        learnt.swap(1, k);
+      }
+    }
+    (learnt, backtrack_level)
+  }
+
+  fn bump(&mut self, variable: usize) {
+    self.activity[variable] += self.increment;
+    if self.activity[variable] > 1e100 {
+      // Scaling all activities keeps their order
+      for activity in self.activity.iter_mut() {
+        *activity *= 1e-100;
+      }
+      self.increment *= 1e-100;
+    }
+    self.order.update(variable, &self.activity);
+  }
+
+  fn backtrack(&mut self, level: usize) {
+    if self.trail_limits.len() <= level {
+      return;
+    }
+    let start = self.trail_limits[level];
+    for &literal in &self.trail[start..] {
+      let variable = variable(literal);
+      self.phases[variable] = literal & 1 == 0;
+      self.values[variable] = None;
+      self.reasons[variable] = None;
+      self.order.insert(variable, &self.activity);
+    }
+    self.trail.truncate(start);
+    self.trail_limits.truncate(level);
+    self.propagated = start;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn satisfies(clauses: &[Vec<i32>], solution: &[bool]) -> bool {
+    clauses.iter().all(|clause| {
+      clause
+        .iter()
+        .any(|&literal| solution[literal.unsigned_abs() as usize - 1] == (literal > 0))
+    })
+  }
+
+  #[test]
+  fn finds_solutions() {
+    let clauses = vec![
+      vec![1, 2],
+      vec![-1, 3],
+      vec![-2, -3],
+      vec![-3, 4],
+      vec![-4, -1, 2],
+    ];
+    let mut solver = Solver::new(4);
+    for clause in &clauses {
+      solver.add_clause(clause);
+    }
+    match solver.solve(1000) {
+      Solution::Satisfiable(solution) => assert!(satisfies(&clauses, &solution)),
+      solution => panic!("{:?}", solution),
+    }
+  }
+
+  #[test]
+  fn proves_the_pigeonhole_principle() {
+    // 5 pigeons don't fit in 4 holes, variable 4 * p + h + 1 meaning that
+    // pigeon p is in hole h
+    let (pigeons, holes) = (5, 4);
+    let mut solver = Solver::new(pigeons * holes);
+    let var = |pigeon: usize, hole: usize| (pigeon * holes + hole + 1) as i32;
+    for pigeon in 0..pigeons {
+      solver.add_clause(&(0..holes).map(|hole| var(pigeon, hole)).collect::<Vec<_>>());
+    }
+    for hole in 0..holes {
+      for a in 0..pigeons {
+        for b in a + 1..pigeons {
+          solver.add_clause(&[-var(a, hole), -var(b, hole)]);
+        }
+      }
+    }
+    assert_eq!(solver.solve(100_000), Solution::Unsatisfiable);
+  }
+
+  #[test]
+  fn orders_variables_by_activity() {
+    let activity = [3.0, 1.0, 4.0, 1.5, 5.0];
+    let mut order = VariableOrder::new(activity.len());
+    while order.pop(&[0.0; 5]).is_some() {}
+    for variable in 0..activity.len() {
+      order.insert(variable, &activity);
+    }
+    order.insert(2, &activity);
+    let popped: Vec<usize> = std::iter::from_fn(|| order.pop(&activity)).collect();
+    assert_eq!(popped, vec![4, 2, 0, 3, 1]);
+  }
+}
@@ -0,0 +1,180 @@
+use crate::life::{CellStates, ParsePatternError, Pattern, Rule};
+use std::str::FromStr;
+
+/// Characters that can be in a URL fragment as they are, apart from letters
+/// and digits. The RLE of the cells never needs escaping.
+const UNESCAPED: &str = "-_.~/:,!$()*+;@";
+
+/// Boards with more cells than this aren’t shared, as their links would be
+/// too long.
+pub const MAX_SHARED_CELLS: usize = 10_000;
+
+/// What it takes for someone else to see the same board, written in the
+/// fragment of a URL.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Snapshot {
+  pub rule: Rule,
+  pub generation: u64,
+  pub zoom: f64,
+  pub offset: (f64, f64),
+  pub cells: CellStates,
+}
+
+impl Snapshot {
+  /// Writes the snapshot as `r=B3/S23&g=0&z=1&o=0,0&p=-1,-1&c=bo$2bo$3o!`:
+  /// the rule, generation, zoom and offset, then the top left corner of the
+  /// cells and their RLE.
+  pub fn to_fragment(&self) -> String {
+    let pattern = Pattern::new(self.cells.clone());
+    let position = pattern
+      .bounding_box()
+      .map_or((0, 0), |(top_left, _)| (top_left.x, top_left.y));
+    // Without a name or comments, the header is the first line
+    let rle: String = pattern.to_rle().lines().skip(1).collect();
+    format!(
+      "r={}&g={}&z={}&o={},{}&p={},{}&c={}",
+      escape(&self.rule.to_string()),
+      self.generation,
+      self.zoom,
+      self.offset.0,
+      self.offset.1,
+      position.0,
+      position.1,
+      rle
+    )
+  }
+
+  pub fn from_fragment(fragment: &str) -> Result<Self, ParsePatternError> {
+    let fragment = fragment.strip_prefix('#').unwrap_or(fragment);
+    let field = |key: &str| {
+      fragment
+        .split('&')
+        .find_map(|field| field.strip_prefix(key)?.strip_prefix('='))
+        .ok_or_else(|| ParsePatternError(format!("Missing '{}' in the link", key)))
+    };
+    let invalid = |key: &str| ParsePatternError(format!("Invalid '{}' in the link", key));
+
+    let rule = unescape(field("r")?)
+      .parse::<Rule>()
+      .map_err(|error| ParsePatternError(error.to_string()))?;
+    let generation = field("g")?.parse().map_err(|_| invalid("g"))?;
+    let zoom = field("z")?
+      .parse()
+      .ok()
+      .filter(|zoom: &f64| zoom.is_finite() && *zoom > 0.0)
+      .ok_or_else(|| invalid("z"))?;
+    let offset = pair(field("o")?)
+      .filter(|(x, y): &(f64, f64)| x.is_finite() && y.is_finite())
+      .ok_or_else(|| invalid("o"))?;
+    let (x, y): (i32, i32) = pair(field("p")?).ok_or_else(|| invalid("p"))?;
+    let rle = format!("#P {} {}\n{}", x, y, field("c")?);
+    Ok(Self {
+      rule,
+      generation,
+      zoom,
+      offset,
+      cells: Pattern::from_rle_within(&rle, MAX_SHARED_CELLS)?.cells,
+    })
+  }
+}
+
+/// Reads two comma-separated numbers.
+fn pair<T: FromStr>(value: &str) -> Option<(T, T)> {
+  let (x, y) = value.split_once(',')?;
+  Some((x.parse().ok()?, y.parse().ok()?))
+}
+
+/// Percent-encodes the characters that can’t be in a URL fragment, or would
+/// be read as separators.
+fn escape(text: &str) -> String {
+  let mut escaped = String::new();
+  for c in text.chars() {
+    if c.is_ascii_alphanumeric() || UNESCAPED.contains(c) {
+      escaped.push(c);
+    } else {
+      let mut bytes = [0; 4];
+      for byte in c.encode_utf8(&mut bytes).bytes() {
+        escaped.push_str(&format!("%{:02X}", byte));
+      }
+    }
+  }
+  escaped
+}
+
+/// Decodes percent-encoded characters, leaving invalid ones as they are.
+fn unescape(text: &str) -> String {
+  let mut bytes = vec![];
+  let mut rest = text.as_bytes();
+  while let Some((&byte, tail)) = rest.split_first() {
+    let decoded = (byte == b'%')
+      .then(|| tail.get(..2))
+      .flatten()
+      .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+    match decoded {
+      Some(decoded) => {
+        bytes.push(decoded);
+        rest = &tail[2..];
+      }
+      None => {
+        bytes.push(byte);
+        rest = tail;
+      }
+    }
+  }
+  String::from_utf8_lossy(&bytes).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use lexicon::Cell;
+
+  #[test]
+  fn round_trips_fragments() {
+    let snapshot = Snapshot {
+      rule: "B3/S23/C4:T20,10".parse().unwrap(),
+      generation: 1234,
+      zoom: 0.75,
+      offset: (-12.5, 300.0),
+      cells: [((-3, 2), 1), ((0, 0), 2), ((10, 5), 3)]
+        .iter()
+        .map(|&((x, y), state)| (Cell { x, y }, state))
+        .collect(),
+    };
+    let fragment = snapshot.to_fragment();
+    assert!(!fragment.contains(['\n', '#', ' ']));
+    assert_eq!(
+      Snapshot::from_fragment(&format!("#{}", fragment)),
+      Ok(snapshot)
+    );
+
+    let empty = Snapshot::from_fragment("r=B3/S23&g=0&z=1&o=0,0&p=0,0&c=!").unwrap();
+    assert!(empty.cells.is_empty());
+    assert!(Snapshot::from_fragment("r=B3/S23&g=0&z=1&o=0,0&p=0,0").is_err());
+    assert!(Snapshot::from_fragment("r=B3/S23&g=-1&z=1&o=0,0&p=0,0&c=!").is_err());
+  }
+
+  #[test]
+  fn rejects_unusable_views_and_huge_boards() {
+    let fragment = |zoom: &str, offset: &str, cells: &str| {
+      Snapshot::from_fragment(&format!(
+        "r=B3/S23&g=0&z={}&o={}&p=0,0&c={}",
+        zoom, offset, cells
+      ))
+    };
+    assert!(fragment("1", "0,0", "3o!").is_ok());
+    for zoom in ["0", "-1", "NaN", "inf"] {
+      assert!(fragment(zoom, "0,0", "3o!").is_err(), "{}", zoom);
+    }
+    assert!(fragment("1", "NaN,0", "3o!").is_err());
+    assert!(fragment("1", "0,-inf", "3o!").is_err());
+    assert!(fragment("1", "0,0", &format!("{}o!", MAX_SHARED_CELLS + 1)).is_err());
+    assert!(fragment("1", "0,0", "2000000000o!").is_err());
+  }
+
+  #[test]
+  fn escapes_separators() {
+    assert_eq!(escape("a b&c=é/"), "a%20b%26c%3D%C3%A9/");
+    assert_eq!(unescape("a%20b%26c%3D%C3%A9/%zz%2"), "a b&c=é/%zz%2");
+  }
+}
@@ -0,0 +1,206 @@
+use crate::life::CellSet;
+use lexicon::Cell;
+
+/// A symmetry group of soups, named like on Catagolue: the pattern is
+/// mapped onto itself by every transformation, all of them around the same
+/// center. Centers are doubled, so that they can be on the middle of an
+/// edge or on a corner of a cell.
+pub struct Symmetry {
+  pub name: &'static str,
+  transformations: &'static [(i32, i32, i32, i32)],
+  center: (i32, i32),
+}
+
+const IDENTITY: (i32, i32, i32, i32) = (1, 0, 0, 1);
+const ROTATE_90: (i32, i32, i32, i32) = (0, -1, 1, 0);
+const ROTATE_180: (i32, i32, i32, i32) = (-1, 0, 0, -1);
+const ROTATE_270: (i32, i32, i32, i32) = (0, 1, -1, 0);
+const FLIP_X: (i32, i32, i32, i32) = (-1, 0, 0, 1);
+const FLIP_Y: (i32, i32, i32, i32) = (1, 0, 0, -1);
+const TRANSPOSE: (i32, i32, i32, i32) = (0, 1, 1, 0);
+const ANTI_TRANSPOSE: (i32, i32, i32, i32) = (0, -1, -1, 0);
+
+const C2: [(i32, i32, i32, i32); 2] = [IDENTITY, ROTATE_180];
+const C4: [(i32, i32, i32, i32); 4] = [IDENTITY, ROTATE_90, ROTATE_180, ROTATE_270];
+const D2_ORTHOGONAL: [(i32, i32, i32, i32); 2] = [IDENTITY, FLIP_Y];
+const D4_ORTHOGONAL: [(i32, i32, i32, i32); 4] = [IDENTITY, FLIP_X, FLIP_Y, ROTATE_180];
+const D4_DIAGONAL: [(i32, i32, i32, i32); 4] = [IDENTITY, TRANSPOSE, ANTI_TRANSPOSE, ROTATE_180];
+const D8: [(i32, i32, i32, i32); 8] = [
+  IDENTITY,
+  ROTATE_90,
+  ROTATE_180,
+  ROTATE_270,
+  FLIP_X,
+  FLIP_Y,
+  TRANSPOSE,
+  ANTI_TRANSPOSE,
+];
+
+pub const SYMMETRIES: [Symmetry; 16] = [
+  Symmetry {
+    name: "C1",
+    transformations: &[IDENTITY],
+    center: (0, 0),
+  },
+  Symmetry {
+    name: "C2_1",
+    transformations: &C2,
+    center: (0, 0),
+  },
+  Symmetry {
+    name: "C2_2",
+    transformations: &C2,
+    center: (0, -1),
+  },
+  Symmetry {
+    name: "C2_4",
+    transformations: &C2,
+    center: (-1, -1),
+  },
+  Symmetry {
+    name: "C4_1",
+    transformations: &C4,
+    center: (0, 0),
+  },
+  Symmetry {
+    name: "C4_4",
+    transformations: &C4,
+    center: (-1, -1),
+  },
+  Symmetry {
+    name: "D2_+1",
+    transformations: &D2_ORTHOGONAL,
+    center: (0, 0),
+  },
+  Symmetry {
+    name: "D2_+2",
+    transformations: &D2_ORTHOGONAL,
+    center: (0, -1),
+  },
+  Symmetry {
+    name: "D2_x",
+    transformations: &[IDENTITY, TRANSPOSE],
+    center: (0, 0),
+  },
+  Symmetry {
+    name: "D4_+1",
+    transformations: &D4_ORTHOGONAL,
+    center: (0, 0),
+  },
+  Symmetry {
+    name: "D4_+2",
+    transformations: &D4_ORTHOGONAL,
+    center: (0, -1),
+  },
+  Symmetry {
+    name: "D4_+4",
+    transformations: &D4_ORTHOGONAL,
+    center: (-1, -1),
+  },
+  Symmetry {
+    name: "D4_x1",
+    transformations: &D4_DIAGONAL,
+    center: (0, 0),
+  },
+  Symmetry {
+    name: "D4_x4",
+    transformations: &D4_DIAGONAL,
+    center: (-1, -1),
+  },
+  Symmetry {
+    name: "D8_1",
+    transformations: &D8,
+    center: (0, 0),
+  },
+  Symmetry {
+    name: "D8_4",
+    transformations: &D8,
+    center: (-1, -1),
+  },
+];
+
+impl Symmetry {
+  /// The cells a cell is mapped to, including itself.
+  fn images(&self, cell: Cell) -> impl Iterator<Item = Cell> + '_ {
+    let (cx, cy) = self.center;
+    self.transformations.iter().map(move |&(a, b, c, d)| {
+      // Around the center: M (cell - center) + center
+      let (dx, dy) = (cx - (a * cx + b * cy), cy - (c * cx + d * cy));
+      Cell {
+        x: a * cell.x + b * cell.y + dx / 2,
+        y: c * cell.x + d * cell.y + dy / 2,
+      }
+    })
+  }
+}
+
+/// A small pseudo-random number generator (SplitMix64), so that soups are
+/// the same everywhere for a given seed.
+struct Random(u64);
+
+impl Random {
+  /// Seeded with the FNV-1a hash of a string.
+  fn new(seed: &str) -> Self {
+    let hash = seed.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+      (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    Self(hash)
+  }
+
+  fn next_f64(&mut self) -> f64 {
+    self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = self.0;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1_u64 << 53) as f64
+  }
+}
+
+/// A random square of cells with the given proportion of alive ones, along
+/// with its images by the symmetry.
+pub fn soup(seed: &str, size: u32, density: f64, symmetry: &Symmetry) -> CellSet {
+  let mut random = Random::new(seed);
+  let mut cells = CellSet::new();
+  for y in 0..size as i32 {
+    for x in 0..size as i32 {
+      if random.next_f64() < density {
+        cells.extend(symmetry.images(Cell { x, y }));
+      }
+    }
+  }
+  cells
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn soups_are_reproducible() {
+    let symmetry = &SYMMETRIES[0];
+    let soup_1 = soup("k_abc", 16, 0.5, symmetry);
+    assert_eq!(soup("k_abc", 16, 0.5, symmetry), soup_1);
+    assert_ne!(soup("k_abd", 16, 0.5, symmetry), soup_1);
+    assert!((90..170).contains(&soup_1.len()));
+    assert!(soup("k_abc", 16, 0.0, symmetry).is_empty());
+    assert_eq!(soup("k_abc", 16, 1.0, symmetry).len(), 256);
+  }
+
+  #[test]
+  fn soups_have_their_symmetry() {
+    for symmetry in &SYMMETRIES {
+      let cells = soup("seed", 8, 0.3, symmetry);
+      for &cell in &cells {
+        for image in symmetry.images(cell) {
+          assert!(cells.contains(&image), "{} {:?}", symmetry.name, image);
+        }
+      }
+    }
+    let c2_4 = &SYMMETRIES[3];
+    assert_eq!(
+      c2_4.images(Cell { x: 0, y: 0 }).collect::<Vec<_>>(),
+      vec![Cell { x: 0, y: 0 }, Cell { x: -1, y: -1 }]
+    );
+  }
+}
@@ -0,0 +1,64 @@
+use crate::life::engine::{bounding_box, Area, LifeEngine};
+use crate::life::{step, CellStates, Rule};
+use lexicon::Cell;
+
+/// A universe stored as a map of the cells that aren't dead. It is the
+/// slowest engine, but the only one that runs every rule.
+pub struct SparseLife {
+  rule: Rule,
+  cells: CellStates,
+}
+
+impl SparseLife {
+  pub fn new(rule: &Rule) -> Self {
+    Self {
+      rule: rule.clone(),
+      cells: CellStates::new(),
+    }
+  }
+}
+
+impl LifeEngine for SparseLife {
+  fn set_rule(&mut self, rule: &Rule) {
+    self.rule = rule.clone();
+  }
+
+  fn step(&mut self) {
+    self.cells = step(&self.cells, &self.rule);
+  }
+
+  fn get_cell(&self, cell: Cell) -> u8 {
+    self.cells.get(&cell).copied().unwrap_or(0)
+  }
+
+  fn set_cell(&mut self, cell: Cell, state: u8) {
+    if state == 0 {
+      self.cells.remove(&cell);
+    } else {
+      self.cells.insert(cell, state);
+    }
+  }
+
+  fn population(&self) -> u64 {
+    self.cells.len() as u64
+  }
+
+  fn bounding_box(&self) -> Option<(Cell, Cell)> {
+    bounding_box(self.cells.keys().copied())
+  }
+
+  fn viewport(&self, (x, y): &Area) -> Box<dyn Iterator<Item = (Cell, u8)> + '_> {
+    let (x, y) = (x.clone(), y.clone());
+    Box::new(
+      self
+        .cells
+        .iter()
+        .filter(move |(cell, _)| x.contains(&cell.x) && y.contains(&cell.y))
+        .map(|(&cell, &state)| (cell, state)),
+    )
+  }
+
+  fn cells(&self) -> CellStates {
+    self.cells.clone()
+  }
+}
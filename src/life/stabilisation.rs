@@ -0,0 +1,267 @@
+use crate::life::census::{census_within, Object};
+use crate::life::engine::bounding_box;
+use crate::life::{CellSet, Periodicity, Rule};
+use lexicon::Cell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// Spaceships closer than this to another object may still hit it.
+const ESCAPE_DISTANCE: i32 = 2;
+
+/// How many of the last populations must repeat before taking a census, with
+/// a period of up to half of them.
+const POPULATION_WINDOW: usize = 64;
+
+/// How many generations the objects of the census are run for, as ash only
+/// has objects of short periods.
+const ASH_GENERATIONS: u64 = 128;
+
+/// When a pattern turned into ash: still lifes, oscillators and spaceships
+/// flying away from everything else.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Stabilisation {
+  /// The first generation from which the pattern only is ash.
+  pub lifespan: u64,
+  /// Whether the pattern may have stabilised earlier than `lifespan`, when
+  /// the generations before it weren't recorded.
+  pub at_most: bool,
+  /// The population once stabilised, escaping spaceships included.
+  pub population: u64,
+}
+
+impl fmt::Display for Stabilisation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "Stabilised {} generation {}, final population {}",
+      if self.at_most { "by" } else { "at" },
+      self.lifespan,
+      self.population
+    )
+  }
+}
+
+/// A hash of some cells that is the sum of the hashes of the cells, so that
+/// the hash of disjoint objects is the sum of their hashes.
+fn cells_hash<'a>(cells: impl Iterator<Item = &'a Cell>) -> u64 {
+  cells.fold(0, |sum, cell| {
+    let mut hasher = DefaultHasher::new();
+    cell.hash(&mut hasher);
+    sum.wrapping_add(hasher.finish())
+  })
+}
+
+/// The bounding box of an object over all its phases, with the distance it
+/// goes per generation.
+struct Flight {
+  top_left: Cell,
+  bottom_right: Cell,
+  velocity: (f64, f64),
+}
+
+impl Flight {
+  /// Whether the first object is ahead of the second one on every axis it
+  /// goes faster along, so that they never meet.
+  fn escapes(&self, other: &Flight) -> bool {
+    let velocity = (
+      self.velocity.0 - other.velocity.0,
+      self.velocity.1 - other.velocity.1,
+    );
+    let ahead = |velocity: f64, (min, max): (i32, i32), (other_min, other_max): (i32, i32)| {
+      if velocity > 0.0 {
+        min > other_max + ESCAPE_DISTANCE
+      } else if velocity < 0.0 {
+        max < other_min - ESCAPE_DISTANCE
+      } else {
+        true
+      }
+    };
+    ahead(
+      velocity.0,
+      (self.top_left.x, self.bottom_right.x),
+      (other.top_left.x, other.bottom_right.x),
+    ) && ahead(
+      velocity.1,
+      (self.top_left.y, self.bottom_right.y),
+      (other.top_left.y, other.bottom_right.y),
+    )
+  }
+}
+
+/// Watches a pattern generation after generation to tell when it has
+/// stabilised, and how long it took.
+#[derive(Default)]
+pub struct StabilisationDetector {
+  /// The first recorded generation.
+  start: u64,
+  /// The hash of every recorded generation, in order.
+  hashes: Vec<u64>,
+  /// The last populations, up to `POPULATION_WINDOW` of them.
+  populations: VecDeque<usize>,
+  stabilisation: Option<Stabilisation>,
+}
+
+impl StabilisationDetector {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn stabilisation(&self) -> Option<Stabilisation> {
+    self.stabilisation
+  }
+
+  /// Adds a generation, following the previous one.
+  pub fn record(&mut self, generation: u64, cells: &CellSet) {
+    if generation != self.start + self.hashes.len() as u64 {
+      *self = Self::new();
+      self.start = generation;
+    }
+    self.hashes.push(cells_hash(cells.iter()));
+    if self.populations.len() == POPULATION_WINDOW {
+      self.populations.pop_front();
+    }
+    self.populations.push_back(cells.len());
+  }
+
+  /// Whether the last populations repeat, as they do once the pattern is
+  /// ash. This is much cheaper than a census.
+  fn population_repeats(&self) -> bool {
+    let populations = &self.populations;
+    (1..=populations.len() / 2)
+      .any(|period| (period..populations.len()).all(|i| populations[i] == populations[i - period]))
+  }
+
+  /// Once the population repeats, takes a census of the last recorded
+  /// generation to tell whether it is ash, and if so runs that ash backwards
+  /// to find the first recorded generation it matches.
+  pub fn check(&mut self, cells: &CellSet, rule: &Rule) -> Option<Stabilisation> {
+    if self.stabilisation.is_some() || self.hashes.is_empty() {
+      return self.stabilisation;
+    }
+    if !self.population_repeats() {
+      return None;
+    }
+    let mut objects = vec![];
+    for object in census_within(cells, rule, ASH_GENERATIONS) {
+      let (period, offset) = match object.periodicity {
+        Periodicity::StillLife => (1, (0, 0)),
+        Periodicity::Oscillator { period } => (period, (0, 0)),
+        Periodicity::Spaceship { period, offset } => (period, offset),
+        _ => return None,
+      };
+      let (_, phases) = Object::analyse(object.cells, rule, period + 1);
+      let (top_left, bottom_right) = bounding_box(phases.iter().flatten().copied())?;
+      let velocity = (
+        offset.0 as f64 / period as f64,
+        offset.1 as f64 / period as f64,
+      );
+      let flight = Flight {
+        top_left,
+        bottom_right,
+        velocity,
+      };
+      objects.push((phases, offset, flight));
+    }
+    for (i, (_, offset, flight)) in objects.iter().enumerate() {
+      let escapes = objects
+        .iter()
+        .enumerate()
+        .all(|(j, (_, _, other))| i == j || flight.escapes(other));
+      if *offset != (0, 0) && !escapes {
+        return None;
+      }
+    }
+
+    // The ash run back in time, as long as it matches the recorded hashes
+    let last = self.hashes.len() - 1;
+    let matching = (0..=last)
+      .take_while(|&back| {
+        let hash = objects.iter().fold(0_u64, |sum, (phases, offset, _)| {
+          let generation = -(back as i64);
+          let period = phases.len() as i64;
+          let phase = &phases[generation.rem_euclid(period) as usize];
+          let periods = generation.div_euclid(period) as i32;
+          let cells = phase.iter().map(|cell| Cell {
+            x: cell.x + periods * offset.0,
+            y: cell.y + periods * offset.1,
+          });
+          sum.wrapping_add(cells_hash(cells.collect::<Vec<_>>().iter()))
+        });
+        hash == self.hashes[last - back]
+      })
+      .count();
+    self.stabilisation = Some(Stabilisation {
+      lifespan: self.start + (last + 1 - matching) as u64,
+      at_most: matching == last + 1 && self.start > 0,
+      population: cells.len() as u64,
+    });
+    self.stabilisation
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::life::tick;
+
+  fn lifespan(pattern: &[(i32, i32)], generations: u64) -> Option<Stabilisation> {
+    let rule = Rule::conway();
+    let mut cells: CellSet = pattern.iter().map(|&(x, y)| Cell { x, y }).collect();
+    let mut detector = StabilisationDetector::new();
+    for generation in 0..=generations {
+      if generation > 0 {
+        cells = tick(&cells, &rule);
+      }
+      detector.record(generation, &cells);
+    }
+    detector.check(&cells, &rule)
+  }
+
+  #[test]
+  fn finds_the_lifespan_of_methuselahs() {
+    let r_pentomino = [(1, 0), (2, 0), (0, 1), (1, 1), (1, 2)];
+    assert_eq!(lifespan(&r_pentomino, 1000), None);
+    assert_eq!(
+      lifespan(&r_pentomino, 1200),
+      Some(Stabilisation {
+        lifespan: 1103,
+        at_most: false,
+        population: 116
+      })
+    );
+    let diehard = [(6, 0), (0, 1), (1, 1), (1, 2), (5, 2), (6, 2), (7, 2)];
+    // Its population must have stayed at 0 for a while
+    assert_eq!(lifespan(&diehard, 150), None);
+    assert_eq!(
+      lifespan(&diehard, 200),
+      Some(Stabilisation {
+        lifespan: 130,
+        at_most: false,
+        population: 0
+      })
+    );
+  }
+
+  #[test]
+  fn restarts_when_generations_are_skipped() {
+    let rule = Rule::conway();
+    let block: CellSet = [(0, 0), (1, 0), (0, 1), (1, 1)]
+      .iter()
+      .map(|&(x, y)| Cell { x, y })
+      .collect();
+    let mut detector = StabilisationDetector::new();
+    detector.record(0, &block);
+    detector.record(1024, &block);
+    detector.record(1025, &block);
+    assert_eq!(
+      detector.check(&block, &rule),
+      Some(Stabilisation {
+        lifespan: 1024,
+        at_most: true,
+        population: 4
+      })
+    );
+  }
+}
@@ -0,0 +1,547 @@
+use crate::life::{CellStates, Neighborhood, ParseRuleError, Topology};
+use lexicon::Cell;
+use std::collections::HashMap;
+
+pub type Rgb = (u8, u8, u8);
+
+/// A multi-state rule loaded from a Golly `.rule` file, either from its
+/// `@TABLE` or its `@TREE` section.
+#[derive(Clone, PartialEq, Debug)]
+pub struct RuleTable {
+  pub name: String,
+  pub states: u8,
+  pub neighborhood: Neighborhood,
+  /// The colors given by the `@COLORS` section, for some of the states.
+  pub colors: Vec<(u8, Rgb)>,
+  lookup: Lookup,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Lookup {
+  /// The transitions in the order they were written, the first one that
+  /// matches giving the next state. With `permute` symmetry, neighbors can
+  /// match in any order.
+  Table {
+    transitions: Vec<Transition>,
+    permute: bool,
+  },
+  /// Nodes whose children are chosen by the state of one neighbor after the
+  /// other, in the order of `tree_order`, those of level 1 giving the next
+  /// state. The root is the last one.
+  Tree(Vec<Vec<u32>>),
+}
+
+/// A set of states.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct States([u64; 4]);
+
+impl States {
+  fn single(state: u8) -> Self {
+    let mut states = States([0; 4]);
+    states.0[state as usize / 64] |= 1 << (state % 64);
+    states
+  }
+
+  fn from_slice(values: &[u8]) -> Self {
+    let mut states = States([0; 4]);
+    for &state in values {
+      states.0[state as usize / 64] |= 1 << (state % 64);
+    }
+    states
+  }
+
+  fn contains(&self, state: u8) -> bool {
+    self.0[state as usize / 64] & 1 << (state % 64) != 0
+  }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+struct Transition {
+  cell: States,
+  neighbors: Vec<States>,
+  next: u8,
+}
+
+/// Indices in the order of `Neighborhood::offsets` of the neighbors a rule
+/// tree looks at, before the cell itself.
+fn tree_order(neighborhood: Neighborhood) -> &'static [usize] {
+  match neighborhood {
+    // NW, NE, SW, SE, N, W, E, S
+    Neighborhood::Moore => &[7, 1, 5, 3, 0, 6, 2, 4],
+    // N, W, E, S
+    _ => &[0, 3, 1, 2],
+  }
+}
+
+fn error(line: usize, message: &str) -> ParseRuleError {
+  ParseRuleError(format!("Line {}: {}", line + 1, message))
+}
+
+impl RuleTable {
+  /// The next state of a cell, given those of its neighbors in the order of
+  /// `Neighborhood::offsets`. Cells stay the same when no transition
+  /// matches.
+  pub fn next_state(&self, cell: u8, neighbors: &[u8]) -> u8 {
+    match &self.lookup {
+      Lookup::Table {
+        transitions,
+        permute,
+      } => transitions
+        .iter()
+        .find(|transition| {
+          transition.cell.contains(cell)
+            && if *permute {
+              permuted_match(&transition.neighbors, neighbors, 0)
+            } else {
+              transition
+                .neighbors
+                .iter()
+                .zip(neighbors)
+                .all(|(states, &state)| states.contains(state))
+            }
+        })
+        .map_or(cell, |transition| transition.next),
+      Lookup::Tree(nodes) => {
+        let mut node = nodes.len() - 1;
+        for &i in tree_order(self.neighborhood) {
+          node = nodes[node][neighbors[i] as usize] as usize;
+        }
+        nodes[node][cell as usize] as u8
+      }
+    }
+  }
+
+  /// The name given by the `@RULE` line of a `.rule` file, without parsing
+  /// the rest of it.
+  pub fn name_of(file: &str) -> Option<&str> {
+    file.lines().find_map(|line| {
+      let mut words = line.split('#').next()?.split_whitespace();
+      (words.next() == Some("@RULE"))
+        .then(|| words.next())
+        .flatten()
+    })
+  }
+
+  /// Parses a `.rule` file. Only its `@RULE`, `@TABLE`, `@TREE` and
+  /// `@COLORS` sections are read.
+  pub fn parse(file: &str) -> Result<Self, ParseRuleError> {
+    let mut sections: HashMap<&str, Vec<(usize, &str)>> = HashMap::new();
+    let mut name = None;
+    let mut section = "";
+    for (i, line) in file.lines().enumerate() {
+      let line = line.split('#').next().unwrap().trim();
+      if let Some(header) = line.strip_prefix('@') {
+        let mut words = header.split_whitespace();
+        section = words.next().unwrap_or("");
+        if section == "RULE" {
+          name = words.next().map(str::to_string);
+        }
+      } else if !line.is_empty() {
+        sections.entry(section).or_default().push((i, line));
+      }
+    }
+
+    let name = name.ok_or_else(|| ParseRuleError("A rule file starts with @RULE".to_string()))?;
+    let mut table = match (sections.get("TABLE"), sections.get("TREE")) {
+      (Some(lines), _) => parse_table(lines)?,
+      (None, Some(lines)) => parse_tree(lines)?,
+      (None, None) => {
+        return Err(ParseRuleError(
+          "A rule file needs a @TABLE or a @TREE".to_string(),
+        ))
+      }
+    };
+    table.name = name;
+    if let Some(lines) = sections.get("COLORS") {
+      table.colors = parse_colors(lines, table.states)?;
+    }
+    Ok(table)
+  }
+}
+
+/// Whether the neighbors match the sets in some order.
+fn permuted_match(sets: &[States], neighbors: &[u8], used: u32) -> bool {
+  match neighbors.split_first() {
+    None => true,
+    Some((&state, rest)) => sets.iter().enumerate().any(|(i, states)| {
+      used & 1 << i == 0 && states.contains(state) && permuted_match(sets, rest, used | 1 << i)
+    }),
+  }
+}
+
+fn parse_header<'a>(lines: &[(usize, &'a str)], key: &str) -> Option<(usize, &'a str)> {
+  lines.iter().find_map(|&(i, line)| {
+    let (name, value) = line.split_once([':', '='])?;
+    (name.trim() == key).then(|| (i, value.trim()))
+  })
+}
+
+fn parse_states(lines: &[(usize, &str)], key: &str) -> Result<u8, ParseRuleError> {
+  match parse_header(lines, key) {
+    Some((i, value)) => match value.parse::<u32>() {
+      Ok(states @ 2..=255) => Ok(states as u8),
+      _ => Err(error(i, "The number of states must be between 2 and 255")),
+    },
+    None => Err(ParseRuleError(format!("Missing {}", key))),
+  }
+}
+
+fn parse_neighborhood(lines: &[(usize, &str)]) -> Result<Neighborhood, ParseRuleError> {
+  match parse_header(lines, "neighborhood") {
+    Some((_, "Moore")) | None => Ok(Neighborhood::Moore),
+    Some((_, "vonNeumann")) => Ok(Neighborhood::VonNeumann),
+    Some((_, "hexagonal")) => Ok(Neighborhood::Hexagonal),
+    Some((i, neighborhood)) => Err(error(
+      i,
+      &format!("The {} neighborhood is not supported", neighborhood),
+    )),
+  }
+}
+
+/// The permutations of the neighbors given by a symmetry like "rotate4" or
+/// "rotate8reflect", neighbors being listed clockwise.
+fn symmetries(symmetry: &str, size: usize) -> Option<Vec<Vec<usize>>> {
+  let (rotations, reflect) = match symmetry {
+    "none" => (1, false),
+    "reflect" => (1, true),
+    _ => {
+      let symmetry = symmetry.strip_prefix("rotate")?;
+      let (rotations, reflect) = match symmetry.strip_suffix("reflect") {
+        Some(rotations) => (rotations, true),
+        None => (symmetry, false),
+      };
+      (rotations.parse::<usize>().ok()?, reflect)
+    }
+  };
+  if size.checked_rem(rotations) != Some(0) {
+    return None;
+  }
+  let step = size / rotations;
+  let mut permutations: Vec<Vec<usize>> = (0..rotations)
+    .map(|r| (0..size).map(|i| (i + r * step) % size).collect())
+    .collect();
+  if reflect {
+    let reflected: Vec<Vec<usize>> = permutations
+      .iter()
+      .map(|permutation| (0..size).map(|i| permutation[(size - i) % size]).collect())
+      .collect();
+    permutations.extend(reflected);
+  }
+  Some(permutations)
+}
+
+fn parse_table(lines: &[(usize, &str)]) -> Result<RuleTable, ParseRuleError> {
+  let states = parse_states(lines, "n_states")?;
+  let neighborhood = parse_neighborhood(lines)?;
+  let size = neighborhood.size();
+  let (permutations, permute) = match parse_header(lines, "symmetries") {
+    Some((_, "permute")) => (vec![(0..size).collect()], true),
+    Some((i, symmetry)) => match symmetries(symmetry, size) {
+      Some(permutations) => (permutations, false),
+      None => return Err(error(i, &format!("Unknown symmetries '{}'", symmetry))),
+    },
+    None => (vec![(0..size).collect()], false),
+  };
+
+  let parse_state = |i: usize, value: &str| -> Result<u8, ParseRuleError> {
+    match value.trim().parse::<u8>() {
+      Ok(state) if state < states => Ok(state),
+      _ => Err(error(i, &format!("Invalid state '{}'", value))),
+    }
+  };
+
+  let mut variables: HashMap<String, Vec<u8>> = HashMap::new();
+  let mut transitions = vec![];
+  for &(i, line) in lines {
+    if let Some(variable) = line.strip_prefix("var ") {
+      let (name, values) = variable
+        .split_once('=')
+        .ok_or_else(|| error(i, "A variable must look like \"var a={0,1}\""))?;
+      let values = values.trim().trim_start_matches('{').trim_end_matches('}');
+      let mut states = vec![];
+      for value in values.split(',') {
+        match variables.get(value.trim()) {
+          Some(values) => states.extend(values),
+          None => states.push(parse_state(i, value)?),
+        }
+      }
+      variables.insert(name.trim().to_string(), states);
+      continue;
+    }
+    if line.contains(':') {
+      continue;
+    }
+
+    let tokens: Vec<&str> = if line.contains(',') {
+      line.split(',').map(str::trim).collect()
+    } else {
+      line
+        .char_indices()
+        .map(|(i, c)| &line[i..i + c.len_utf8()])
+        .collect()
+    };
+    if tokens.len() != size + 2 {
+      return Err(error(i, &format!("A transition needs {} states", size + 2)));
+    }
+
+    // Variables appearing more than once are bound: they take the same value
+    // everywhere, so each of their values gives a separate transition.
+    let bound: Vec<&str> = tokens
+      .iter()
+      .copied()
+      .filter(|token| variables.contains_key(*token))
+      .filter(|token| tokens.iter().filter(|t| t == &token).count() > 1)
+      .fold(vec![], |mut bound, token| {
+        if !bound.contains(&token) {
+          bound.push(token);
+        }
+        bound
+      });
+    let mut bindings: Vec<HashMap<&str, u8>> = vec![HashMap::new()];
+    for name in &bound {
+      bindings = bindings
+        .into_iter()
+        .flat_map(|binding| {
+          variables[*name].iter().map(move |&value| {
+            let mut binding = binding.clone();
+            binding.insert(*name, value);
+            binding
+          })
+        })
+        .collect();
+    }
+
+    for binding in bindings {
+      let mut sets = vec![];
+      for token in &tokens[..size + 1] {
+        sets.push(match (binding.get(token), variables.get(*token)) {
+          (Some(&value), _) => States::single(value),
+          (None, Some(values)) => States::from_slice(values),
+          (None, None) => States::single(parse_state(i, token)?),
+        });
+      }
+      let next = match binding.get(tokens[size + 1]) {
+        Some(&value) => value,
+        None if variables.contains_key(tokens[size + 1]) => {
+          return Err(error(i, "The new state can only be a variable used before"))
+        }
+        None => parse_state(i, tokens[size + 1])?,
+      };
+      for permutation in &permutations {
+        let transition = Transition {
+          cell: sets[0],
+          neighbors: permutation.iter().map(|&j| sets[j + 1]).collect(),
+          next,
+        };
+        if !transitions.contains(&transition) {
+          transitions.push(transition);
+        }
+      }
+    }
+  }
+
+  Ok(RuleTable {
+    name: String::new(),
+    states,
+    neighborhood,
+    colors: vec![],
+    lookup: Lookup::Table {
+      transitions,
+      permute,
+    },
+  })
+}
+
+fn parse_tree(lines: &[(usize, &str)]) -> Result<RuleTable, ParseRuleError> {
+  let states = parse_states(lines, "num_states")?;
+  let neighborhood = match parse_header(lines, "num_neighbors") {
+    Some((_, "8")) => Neighborhood::Moore,
+    Some((_, "4")) => Neighborhood::VonNeumann,
+    Some((i, _)) => return Err(error(i, "Only 4 or 8 neighbors are supported")),
+    None => return Err(ParseRuleError("Missing num_neighbors".to_string())),
+  };
+
+  let mut nodes: Vec<Vec<u32>> = vec![];
+  let mut levels = vec![];
+  for &(i, line) in lines.iter().filter(|(_, line)| !line.contains('=')) {
+    let numbers = line
+      .split_whitespace()
+      .map(str::parse::<u32>)
+      .collect::<Result<Vec<u32>, _>>()
+      .map_err(|_| error(i, "Invalid node"))?;
+    let (level, children) = numbers
+      .split_first()
+      .ok_or_else(|| error(i, "Invalid node"))?;
+    let valid = children.len() == states as usize
+      && children.iter().all(|&child| match level {
+        1 => child < states as u32,
+        _ => (child as usize) < nodes.len() && levels[child as usize] + 1 == *level,
+      });
+    if !valid {
+      return Err(error(i, "Invalid node"));
+    }
+    levels.push(*level);
+    nodes.push(children.to_vec());
+  }
+  if levels.last() != Some(&(neighborhood.size() as u32 + 1)) {
+    return Err(ParseRuleError(
+      "The last node of a tree must be its root".to_string(),
+    ));
+  }
+
+  Ok(RuleTable {
+    name: String::new(),
+    states,
+    neighborhood,
+    colors: vec![],
+    lookup: Lookup::Tree(nodes),
+  })
+}
+
+/// Lines with a state and its color ("1 255 0 0"), or with two colors to
+/// fade from the first live state to the last one ("255 0 0 0 0 255").
+fn parse_colors(lines: &[(usize, &str)], states: u8) -> Result<Vec<(u8, Rgb)>, ParseRuleError> {
+  let mut colors = vec![];
+  for &(i, line) in lines {
+    let numbers = line
+      .split_whitespace()
+      .map(str::parse::<u8>)
+      .collect::<Result<Vec<u8>, _>>()
+      .map_err(|_| error(i, "Invalid color"))?;
+    match numbers[..] {
+      [state, r, g, b] if state < states => colors.push((state, (r, g, b))),
+      [r1, g1, b1, r2, g2, b2] => {
+        for state in 1..states {
+          let coeff = (state - 1) as f64 / f64::max((states - 2) as f64, 1.0);
+          let mix = |from: u8, to: u8| (from as f64 + (to as f64 - from as f64) * coeff) as u8;
+          colors.push((state, (mix(r1, r2), mix(g1, g2), mix(b1, b2))));
+        }
+      }
+      _ => return Err(error(i, "Invalid color")),
+    }
+  }
+  Ok(colors)
+}
+
+/// Computes the next generation of a rule table, remembering the next state
+/// of each neighborhood as they tend to repeat.
+pub fn tick(cells: &CellStates, table: &RuleTable, topology: &Topology) -> CellStates {
+  let offsets: Vec<(i32, i32)> = table.neighborhood.offsets().collect();
+  let neighbors = |cell: Cell| {
+    offsets.iter().map(move |&(dx, dy)| {
+      topology.normalize(Cell {
+        x: cell.x + dx,
+        y: cell.y + dy,
+      })
+    })
+  };
+
+  let mut candidates: Vec<Cell> = cells.keys().copied().collect();
+  candidates.extend(cells.keys().flat_map(|&cell| neighbors(cell).flatten()));
+  candidates.sort_unstable_by_key(|cell| (cell.y, cell.x));
+  candidates.dedup();
+
+  let mut known: HashMap<Vec<u8>, u8> = HashMap::new();
+  let mut next_cells = CellStates::new();
+  for cell in candidates {
+    let mut key = vec![cells.get(&cell).copied().unwrap_or(0)];
+    key.extend(neighbors(cell).map(|neighbor| {
+      neighbor
+        .and_then(|neighbor| cells.get(&neighbor).copied())
+        .unwrap_or(0)
+    }));
+    let next = *known
+      .entry(key)
+      .or_insert_with_key(|key| table.next_state(key[0], &key[1..]));
+    if next != 0 {
+      next_cells.insert(cell, next);
+    }
+  }
+  next_cells
+}
+
+/// Rules that are always available, by name.
+pub const BUILT_IN: [&str; 1] = [include_str!("rules/WireWorld.rule")];
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn cells(states: &[((i32, i32), u8)]) -> CellStates {
+    states
+      .iter()
+      .map(|&((x, y), state)| (Cell { x, y }, state))
+      .collect()
+  }
+
+  #[test]
+  fn parses_wireworld() {
+    let table = RuleTable::parse(BUILT_IN[0]).unwrap();
+    assert_eq!(table.name, "WireWorld");
+    assert_eq!(table.states, 4);
+    assert_eq!(table.colors.len(), 3);
+    assert_eq!(table.next_state(3, &[1, 0, 0, 0, 0, 0, 0, 0]), 1);
+    assert_eq!(table.next_state(3, &[0, 0, 1, 0, 0, 0, 1, 0]), 1);
+    assert_eq!(table.next_state(3, &[1, 1, 1, 0, 0, 0, 0, 0]), 3);
+    assert_eq!(table.next_state(1, &[0; 8]), 2);
+    assert_eq!(table.next_state(2, &[0; 8]), 3);
+    assert_eq!(table.next_state(0, &[1; 8]), 0);
+  }
+
+  #[test]
+  fn runs_an_electron_along_a_wire() {
+    let table = RuleTable::parse(BUILT_IN[0]).unwrap();
+    let wire = cells(&[((0, 0), 2), ((1, 0), 1), ((2, 0), 3), ((3, 0), 3)]);
+    let next = tick(&wire, &table, &Topology::Infinite);
+    assert_eq!(
+      next,
+      cells(&[((0, 0), 3), ((1, 0), 2), ((2, 0), 1), ((3, 0), 3)])
+    );
+  }
+
+  #[test]
+  fn applies_symmetries_and_bound_variables() {
+    let table = RuleTable::parse(
+      "@RULE Test\n@TABLE\nn_states:3\nneighborhood:vonNeumann\nsymmetries:rotate4\n\
+       var a={1,2}\n0,a,0,a,0,a\n",
+    )
+    .unwrap();
+    assert_eq!(table.next_state(0, &[1, 0, 1, 0]), 1);
+    assert_eq!(table.next_state(0, &[0, 2, 0, 2]), 2);
+    assert_eq!(table.next_state(0, &[0, 1, 0, 2]), 0);
+    assert_eq!(table.next_state(0, &[1, 1, 0, 0]), 0);
+  }
+
+  #[test]
+  fn parses_a_tree() {
+    // Cells take the state of their north neighbor.
+    let table = RuleTable::parse(
+      "@RULE Fall\n@TREE\nnum_states=2\nnum_neighbors=4\nnum_nodes=9\n\
+       1 0 0\n1 1 1\n2 0 0\n2 1 1\n3 2 2\n3 3 3\n4 4 4\n4 5 5\n5 6 7\n",
+    )
+    .unwrap();
+    let next = tick(&cells(&[((0, 0), 1)]), &table, &Topology::Infinite);
+    assert_eq!(next, cells(&[((0, 1), 1)]));
+  }
+
+  #[test]
+  fn rejects_unbound_output_variables() {
+    assert!(RuleTable::parse(
+      "@RULE Test\n@TABLE\nn_states:2\nneighborhood:vonNeumann\nvar a={0,1}\n0,a,0,0,0,a\n0,0,0,0,0,a\n"
+    )
+    .is_err());
+    assert!(
+      RuleTable::parse("@RULE Test\n@TABLE\nn_states:2\nneighborhood:vonNeumann\n0é0000\n")
+        .is_err()
+    );
+  }
+
+  #[test]
+  fn reads_names_without_parsing() {
+    assert_eq!(
+      RuleTable::name_of("# WireWorld\n@RULE WireWorld\n@TABLE\n"),
+      Some("WireWorld")
+    );
+    assert_eq!(RuleTable::name_of("@RULES Test\n"), None);
+  }
+}
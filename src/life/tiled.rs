@@ -0,0 +1,314 @@
+use crate::life::engine::{bounding_box, Area, LifeEngine};
+use crate::life::{CellSet, Neighborhood, Rule};
+use lexicon::Cell;
+use std::collections::{HashMap, HashSet};
+
+const TILE_SIZE: i32 = 64;
+
+/// 64x64 cells, one word per row, the least significant bit being the
+/// leftmost cell.
+type Tile = [u64; TILE_SIZE as usize];
+
+/// A universe stored as bitboard tiles, each generation being computed a
+/// whole row at a time with bitwise operations. Tiles are allocated when
+/// cells are born in them and freed when they die out. Only totalistic rules
+/// are supported.
+pub struct TiledLife {
+  neighborhood: Neighborhood,
+  /// Whether a cell is born, or survives, for each number of alive
+  /// neighbors.
+  birth: [bool; 9],
+  survival: [bool; 9],
+  tiles: HashMap<(i32, i32), Tile>,
+}
+
+fn tile_position(cell: Cell) -> ((i32, i32), usize, usize) {
+  (
+    (cell.x.div_euclid(TILE_SIZE), cell.y.div_euclid(TILE_SIZE)),
+    cell.x.rem_euclid(TILE_SIZE) as usize,
+    cell.y.rem_euclid(TILE_SIZE) as usize,
+  )
+}
+
+/// The outcome of each number of alive neighbors, for a dead or an alive
+/// cell.
+fn counts(rule: &Rule, alive: bool) -> [bool; 9] {
+  let mut counts = [false; 9];
+  let mut neighborhood = 0;
+  let mut neighbors = (0..8).filter(|i| rule.neighborhood().mask() & 1 << i != 0);
+  for outcome in counts.iter_mut() {
+    *outcome = rule.next_state(alive, neighborhood);
+    if let Some(i) = neighbors.next() {
+      neighborhood |= 1 << i;
+    }
+  }
+  counts
+}
+
+/// Adds a word of 1-bit values to a bit-sliced 4-bit counter.
+fn add(counter: &mut [u64; 4], word: u64) {
+  let mut carry = word;
+  for bits in counter.iter_mut() {
+    let next_carry = *bits & carry;
+    *bits ^= carry;
+    carry = next_carry;
+  }
+}
+
+/// The cells of the counter that are equal to `count`.
+fn equal_to(counter: &[u64; 4], count: usize) -> u64 {
+  counter.iter().enumerate().fold(!0, |equal, (i, &bits)| {
+    if count & (1 << i) != 0 {
+      equal & bits
+    } else {
+      equal & !bits
+    }
+  })
+}
+
+impl TiledLife {
+  pub fn new(rule: &Rule) -> Self {
+    Self {
+      neighborhood: rule.neighborhood(),
+      birth: counts(rule, false),
+      survival: counts(rule, true),
+      tiles: HashMap::new(),
+    }
+  }
+
+  pub fn from_cells(cells: &CellSet, rule: &Rule) -> Self {
+    let mut universe = Self::new(rule);
+    for &cell in cells {
+      universe.set_cell(cell, 1);
+    }
+    universe
+  }
+
+  /// A row of the tile (which can be the last row of the tile above, or the
+  /// first of the one below), along with the same row shifted so that each
+  /// cell is aligned with its left and right neighbors.
+  fn extended_row(&self, (tx, ty): (i32, i32), y: i32) -> (u64, u64, u64) {
+    let ty = ty + y.div_euclid(TILE_SIZE);
+    let y = y.rem_euclid(TILE_SIZE) as usize;
+    let row = |tx| self.tiles.get(&(tx, ty)).map_or(0, |tile| tile[y]);
+    let (west, center, east) = (row(tx - 1), row(tx), row(tx + 1));
+    (
+      (center << 1) | (west >> 63),
+      center,
+      (center >> 1) | (east << 63),
+    )
+  }
+
+  fn next_tile(&self, key: (i32, i32)) -> Tile {
+    let mut tile = [0; TILE_SIZE as usize];
+    let mut above = self.extended_row(key, -1);
+    let mut current = self.extended_row(key, 0);
+    for (y, next_row) in tile.iter_mut().enumerate() {
+      let below = self.extended_row(key, y as i32 + 1);
+
+      // In the order of `NEIGHBORS`, each word holding the neighbors in that
+      // direction of the cells of the row.
+      let words = [
+        above.1, above.2, current.2, below.2, below.1, below.0, current.0, above.0,
+      ];
+      let mut counter = [0; 4];
+      for (i, word) in words.into_iter().enumerate() {
+        if self.neighborhood.mask() & 1 << i != 0 {
+          add(&mut counter, word);
+        }
+      }
+
+      let alive = current.1;
+      for count in 0..=8 {
+        let equal = equal_to(&counter, count);
+        if self.birth[count] {
+          *next_row |= equal & !alive;
+        }
+        if self.survival[count] {
+          *next_row |= equal & alive;
+        }
+      }
+
+      above = current;
+      current = below;
+    }
+    tile
+  }
+}
+
+impl LifeEngine for TiledLife {
+  fn set_rule(&mut self, rule: &Rule) {
+    self.neighborhood = rule.neighborhood();
+    self.birth = counts(rule, false);
+    self.survival = counts(rule, true);
+  }
+
+  fn step(&mut self) {
+    let mut candidates = HashSet::new();
+    for (&(tx, ty), tile) in &self.tiles {
+      let north = tile[0] != 0;
+      let south = tile[TILE_SIZE as usize - 1] != 0;
+      let west = tile.iter().any(|&row| row & 1 != 0);
+      let east = tile.iter().any(|&row| row >> 63 != 0);
+      let corners = (
+        tile[0] & 1 != 0,
+        tile[0] >> 63 != 0,
+        tile[TILE_SIZE as usize - 1] & 1 != 0,
+        tile[TILE_SIZE as usize - 1] >> 63 != 0,
+      );
+      let neighbors = [
+        ((0, 0), true),
+        ((0, -1), north),
+        ((0, 1), south),
+        ((-1, 0), west),
+        ((1, 0), east),
+        ((-1, -1), corners.0),
+        ((1, -1), corners.1),
+        ((-1, 1), corners.2),
+        ((1, 1), corners.3),
+      ];
+      for ((dx, dy), needed) in neighbors {
+        if needed {
+          candidates.insert((tx + dx, ty + dy));
+        }
+      }
+    }
+
+    self.tiles = candidates
+      .into_iter()
+      .map(|key| (key, self.next_tile(key)))
+      .filter(|(_, tile)| tile.iter().any(|&row| row != 0))
+      .collect();
+  }
+
+  fn get_cell(&self, cell: Cell) -> u8 {
+    let (key, x, y) = tile_position(cell);
+    self
+      .tiles
+      .get(&key)
+      .map_or(0, |tile| (tile[y] >> x & 1) as u8)
+  }
+
+  /// Every state other than 0 is alive.
+  fn set_cell(&mut self, cell: Cell, state: u8) {
+    let (key, x, y) = tile_position(cell);
+    if state != 0 {
+      self.tiles.entry(key).or_insert([0; TILE_SIZE as usize])[y] |= 1 << x;
+    } else if let Some(tile) = self.tiles.get_mut(&key) {
+      tile[y] &= !(1 << x);
+      if tile.iter().all(|&row| row == 0) {
+        self.tiles.remove(&key);
+      }
+    }
+  }
+
+  fn population(&self) -> u64 {
+    self
+      .tiles
+      .values()
+      .flat_map(|tile| tile.iter())
+      .map(|row| row.count_ones() as u64)
+      .sum()
+  }
+
+  fn bounding_box(&self) -> Option<(Cell, Cell)> {
+    bounding_box(
+      self
+        .tiles
+        .iter()
+        .flat_map(|(&key, tile)| {
+          let mut rows = tile.iter().enumerate().filter(|(_, &row)| row != 0);
+          let top = rows.next()?.0;
+          let bottom = rows.next_back().map_or(top, |(y, _)| y);
+          let row = tile.iter().fold(0, |union, &row| union | row);
+          let (left, right) = (row.trailing_zeros(), 63 - row.leading_zeros());
+          let corner = |x: u32, y: usize| Cell {
+            x: key.0 * TILE_SIZE + x as i32,
+            y: key.1 * TILE_SIZE + y as i32,
+          };
+          Some([corner(left, top), corner(right, bottom)])
+        })
+        .flatten(),
+    )
+  }
+
+  fn viewport(&self, (x, y): &Area) -> Box<dyn Iterator<Item = (Cell, u8)> + '_> {
+    let (x, y) = (x.clone(), y.clone());
+    let keys =
+      (y.start.div_euclid(TILE_SIZE)..=(y.end - 1).div_euclid(TILE_SIZE)).flat_map(move |ty| {
+        (x.start.div_euclid(TILE_SIZE)..=(x.end - 1).div_euclid(TILE_SIZE)).map(move |tx| (tx, ty))
+      });
+    let area = (x, y);
+    Box::new(
+      keys
+        .filter_map(move |key| self.tiles.get(&key).map(|tile| (key, tile)))
+        .flat_map(move |((tx, ty), tile)| {
+          let area = area.clone();
+          tile.iter().enumerate().flat_map(move |(row_y, &row)| {
+            let area = area.clone();
+            (0..TILE_SIZE)
+              .filter(move |&row_x| row & (1 << row_x) != 0)
+              .map(move |row_x| Cell {
+                x: tx * TILE_SIZE + row_x,
+                y: ty * TILE_SIZE + row_y as i32,
+              })
+              .filter(move |cell| area.0.contains(&cell.x) && area.1.contains(&cell.y))
+              .map(|cell| (cell, 1))
+          })
+        }),
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::life::{alive_cells, tick};
+  use lexicon::Lexicon;
+
+  fn assert_same_as_tick(cells: &CellSet, rule: &Rule, generations: usize) {
+    let mut expected = cells.clone();
+    let mut universe = TiledLife::from_cells(cells, rule);
+    for _ in 0..generations {
+      expected = tick(&expected, rule);
+      universe.step();
+      assert_eq!(alive_cells(&universe.cells()), expected);
+    }
+  }
+
+  #[test]
+  fn matches_tick_on_lexicon_patterns() {
+    let rule = Rule::conway();
+    for term in Lexicon::get().terms {
+      let cells: CellSet = term.cells.iter().copied().collect();
+      assert_same_as_tick(&cells, &rule, 8);
+    }
+  }
+
+  #[test]
+  fn matches_tick_across_tile_borders() {
+    let mut seed = 42_u32;
+    let mut cells = CellSet::new();
+    for x in -40..40 {
+      for y in -40..40 {
+        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+        if seed >> 16 & 1 != 0 {
+          cells.insert(Cell { x, y });
+        }
+      }
+    }
+    assert_same_as_tick(&cells, &Rule::conway(), 20);
+    assert_same_as_tick(&cells, &"B36/S23".parse().unwrap(), 20);
+    assert_same_as_tick(&cells, &"B2/S34H".parse().unwrap(), 20);
+    assert_same_as_tick(&cells, &"B3/S023V".parse().unwrap(), 20);
+  }
+
+  #[test]
+  fn frees_empty_tiles() {
+    let mut universe = TiledLife::new(&Rule::conway());
+    universe.set_cell(Cell { x: 63, y: 63 }, 1);
+    universe.step();
+    assert_eq!(universe.population(), 0);
+    assert!(universe.tiles.is_empty());
+  }
+}
@@ -0,0 +1,301 @@
+use crate::life::ParseRuleError;
+use lexicon::Cell;
+use std::fmt;
+use std::str::FromStr;
+
+/// The largest width or height of a bounded universe, far below the point
+/// where coordinates would overflow.
+const MAX_SIZE: u32 = 1_000_000;
+
+/// The shape of the universe, using the same suffixes as Golly ("T100,80"
+/// for a torus, etc.). Bounded universes are centered on the origin: their
+/// top-left cell is at (-width/2, -height/2).
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Topology {
+  #[default]
+  Infinite,
+  /// A plane surrounded by dead cells.
+  Plane {
+    width: u32,
+    height: u32,
+  },
+  Torus {
+    width: u32,
+    height: u32,
+  },
+  /// A torus where the edges that are `twisted` are joined upside down.
+  KleinBottle {
+    width: u32,
+    height: u32,
+    twisted: Edges,
+  },
+  /// A torus where both pairs of edges are joined upside down.
+  CrossSurface {
+    width: u32,
+    height: u32,
+  },
+  /// A square where the top edge is joined to the left one, and the right
+  /// edge to the bottom one.
+  Sphere {
+    size: u32,
+  },
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Edges {
+  /// The top and bottom edges.
+  Horizontal,
+  /// The left and right edges.
+  Vertical,
+}
+
+/// Maps a coordinate to the range `0..size`, telling whether the number of
+/// edges crossed to get there is odd.
+fn wrap(position: i32, size: i32) -> (i32, bool) {
+  (
+    position.rem_euclid(size),
+    position.div_euclid(size).rem_euclid(2) == 1,
+  )
+}
+
+impl Topology {
+  pub fn is_bounded(&self) -> bool {
+    *self != Topology::Infinite
+  }
+
+  /// The top-left cell and dimensions of a bounded universe.
+  pub fn bounds(&self) -> Option<(Cell, u32, u32)> {
+    let (width, height) = match *self {
+      Topology::Infinite => return None,
+      Topology::Plane { width, height }
+      | Topology::Torus { width, height }
+      | Topology::KleinBottle { width, height, .. }
+      | Topology::CrossSurface { width, height } => (width, height),
+      Topology::Sphere { size } => (size, size),
+    };
+    let top_left = Cell {
+      x: -(width as i32 / 2),
+      y: -(height as i32 / 2),
+    };
+    Some((top_left, width, height))
+  }
+
+  pub fn contains(&self, cell: Cell) -> bool {
+    match self.bounds() {
+      None => true,
+      Some((top_left, width, height)) => {
+        cell.x >= top_left.x
+          && cell.x < top_left.x + width as i32
+          && cell.y >= top_left.y
+          && cell.y < top_left.y + height as i32
+      }
+    }
+  }
+
+  /// The cell of the universe at the given position, following the edges
+  /// when it is outside of the bounds, or `None` if it falls off the edge.
+  pub fn normalize(&self, cell: Cell) -> Option<Cell> {
+    let (top_left, width, height) = match self.bounds() {
+      None => return Some(cell),
+      Some(_) if self.contains(cell) => return Some(cell),
+      Some(bounds) => bounds,
+    };
+    let (w, h) = (width as i32, height as i32);
+    let (x, y) = (cell.x - top_left.x, cell.y - top_left.y);
+    let (x, y) = match *self {
+      Topology::Infinite | Topology::Plane { .. } => return None,
+      Topology::Torus { .. } => (x.rem_euclid(w), y.rem_euclid(h)),
+      Topology::KleinBottle { twisted, .. } => {
+        let (x, x_flipped) = wrap(x, w);
+        let (y, y_flipped) = wrap(y, h);
+        match twisted {
+          Edges::Horizontal if y_flipped => (w - 1 - x, y),
+          Edges::Vertical if x_flipped => (x, h - 1 - y),
+          _ => (x, y),
+        }
+      }
+      Topology::CrossSurface { .. } => {
+        let (x, x_flipped) = wrap(x, w);
+        let y = if x_flipped { h - 1 - y } else { y };
+        let (y, y_flipped) = wrap(y, h);
+        (if y_flipped { w - 1 - x } else { x }, y)
+      }
+      // Corners are left out, as they are joined to two cells at once.
+      Topology::Sphere { size } => {
+        let n = size as i32;
+        match (x, y) {
+          (x, y) if x < 0 && (0..n).contains(&y) => (y, -x - 1),
+          (x, y) if x >= n && (0..n).contains(&y) => (y, 2 * n - x - 1),
+          (x, y) if y < 0 && (0..n).contains(&x) => (-y - 1, x),
+          (x, y) if y >= n && (0..n).contains(&x) => (2 * n - y - 1, x),
+          _ => return None,
+        }
+      }
+    };
+    Some(Cell {
+      x: x + top_left.x,
+      y: y + top_left.y,
+    })
+  }
+}
+
+fn parse_size(size: &str) -> Result<u32, ParseRuleError> {
+  match size.trim().parse() {
+    Ok(size @ 1..=MAX_SIZE) => Ok(size),
+    _ => Err(ParseRuleError(format!(
+      "Invalid universe size '{}', expected a number between 1 and {}",
+      size, MAX_SIZE
+    ))),
+  }
+}
+
+impl FromStr for Topology {
+  type Err = ParseRuleError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let s = s.trim();
+    let kind = s.chars().next().map(|c| c.to_ascii_uppercase());
+    let sizes: Vec<&str> = s.get(1..).unwrap_or("").split(',').collect();
+    if sizes
+      .iter()
+      .any(|size| size.contains('+') || size.contains('-'))
+    {
+      return Err(ParseRuleError(
+        "Shifted edges are not supported".to_string(),
+      ));
+    }
+    let twisted = match (sizes.first(), sizes.get(1)) {
+      (Some(width), _) if width.ends_with('*') => Some(Edges::Horizontal),
+      (_, Some(height)) if height.ends_with('*') => Some(Edges::Vertical),
+      _ => None,
+    };
+    let sizes = sizes
+      .iter()
+      .map(|size| parse_size(size.trim_end_matches('*')))
+      .collect::<Result<Vec<u32>, _>>()?;
+    let (width, height) = match sizes[..] {
+      [size] => (size, size),
+      [width, height] => (width, height),
+      _ => {
+        return Err(ParseRuleError(
+          "A universe size must look like \"100,80\"".to_string(),
+        ))
+      }
+    };
+
+    match (kind, twisted) {
+      (Some('P'), None) => Ok(Topology::Plane { width, height }),
+      (Some('T'), None) => Ok(Topology::Torus { width, height }),
+      (Some('K'), Some(twisted)) => Ok(Topology::KleinBottle {
+        width,
+        height,
+        twisted,
+      }),
+      (Some('K'), None) => Err(ParseRuleError(
+        "A Klein bottle needs a twisted edge, like \"K100*,80\"".to_string(),
+      )),
+      (Some('C'), None) => Ok(Topology::CrossSurface { width, height }),
+      (Some('S'), None) if width == height => Ok(Topology::Sphere { size: width }),
+      (Some('S'), None) => Err(ParseRuleError(
+        "A sphere must be as wide as it is high".to_string(),
+      )),
+      (Some('P' | 'T' | 'C' | 'S'), Some(_)) => Err(ParseRuleError(
+        "Only Klein bottles have twisted edges".to_string(),
+      )),
+      _ => Err(ParseRuleError(format!(
+        "Unknown topology '{}', expected P, T, K, C or S",
+        s
+      ))),
+    }
+  }
+}
+
+impl fmt::Display for Topology {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Topology::Infinite => Ok(()),
+      Topology::Plane { width, height } => write!(f, ":P{},{}", width, height),
+      Topology::Torus { width, height } => write!(f, ":T{},{}", width, height),
+      Topology::KleinBottle {
+        width,
+        height,
+        twisted: Edges::Horizontal,
+      } => write!(f, ":K{}*,{}", width, height),
+      Topology::KleinBottle {
+        width,
+        height,
+        twisted: Edges::Vertical,
+      } => write!(f, ":K{},{}*", width, height),
+      Topology::CrossSurface { width, height } => write!(f, ":C{},{}", width, height),
+      Topology::Sphere { size } => write!(f, ":S{}", size),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn cell(x: i32, y: i32) -> Cell {
+    Cell { x, y }
+  }
+
+  #[test]
+  fn parses_torus() {
+    assert_eq!(
+      "T100,80".parse(),
+      Ok(Topology::Torus {
+        width: 100,
+        height: 80
+      })
+    )
+  }
+
+  #[test]
+  fn parses_klein_bottle() {
+    let topology: Topology = "K10,8*".parse().unwrap();
+    assert_eq!(topology.to_string(), ":K10,8*")
+  }
+
+  #[test]
+  fn rejects_unequal_sphere() {
+    assert!("S10,8".parse::<Topology>().is_err())
+  }
+
+  #[test]
+  fn rejects_huge_sizes() {
+    assert!("T1000000,1".parse::<Topology>().is_ok());
+    assert!("T1000001,1".parse::<Topology>().is_err());
+    assert!("P2147483648,10".parse::<Topology>().is_err());
+    assert!("S4294967295".parse::<Topology>().is_err());
+  }
+
+  #[test]
+  fn torus_wraps_around() {
+    let topology: Topology = "T10,8".parse().unwrap();
+    assert_eq!(topology.normalize(cell(5, 0)), Some(cell(-5, 0)));
+    assert_eq!(topology.normalize(cell(0, -5)), Some(cell(0, 3)));
+  }
+
+  #[test]
+  fn plane_drops_cells_outside() {
+    let topology: Topology = "P10,8".parse().unwrap();
+    assert_eq!(topology.normalize(cell(4, 3)), Some(cell(4, 3)));
+    assert_eq!(topology.normalize(cell(5, 0)), None);
+  }
+
+  #[test]
+  fn klein_bottle_flips_twisted_edges() {
+    let topology: Topology = "K10*,8".parse().unwrap();
+    assert_eq!(topology.normalize(cell(-5, -5)), Some(cell(4, 3)));
+    assert_eq!(topology.normalize(cell(5, -4)), Some(cell(-5, -4)));
+  }
+
+  #[test]
+  fn sphere_joins_adjacent_edges() {
+    let topology: Topology = "S10".parse().unwrap();
+    assert_eq!(topology.normalize(cell(-2, -6)), Some(cell(-5, -2)));
+    assert_eq!(topology.normalize(cell(-6, -3)), Some(cell(-3, -5)));
+    assert_eq!(topology.normalize(cell(-6, -6)), None);
+  }
+}